@@ -0,0 +1,11 @@
+-- Your SQL goes here
+CREATE TABLE "outbox"(
+                        "id" BIGSERIAL PRIMARY KEY,
+                        "user_id" INTEGER NOT NULL,
+                        "attempts" INTEGER NOT NULL DEFAULT 0,
+                        "last_error" TEXT,
+                        "available_at" TIMESTAMPTZ NOT NULL DEFAULT now(),
+                        "created_at" TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX "outbox_available_at_idx" ON "outbox" ("available_at");
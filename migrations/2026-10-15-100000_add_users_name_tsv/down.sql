@@ -0,0 +1,3 @@
+-- This file should undo anything in "up.sql"
+DROP INDEX "users_name_tsv_idx";
+ALTER TABLE "users" DROP COLUMN "name_tsv";
\ No newline at end of file
@@ -0,0 +1,5 @@
+-- Your SQL goes here
+ALTER TABLE "users"
+    ADD COLUMN "name_tsv" TSVECTOR GENERATED ALWAYS AS (to_tsvector('simple', "name")) STORED;
+
+CREATE INDEX "users_name_tsv_idx" ON "users" USING GIN ("name_tsv");
@@ -0,0 +1,16 @@
+-- This file should undo anything in "up.sql"
+ALTER TABLE "users" DROP CONSTRAINT "users_hair_color_canonical";
+
+-- restores the original values of rows not changed since
+WITH "restored" AS (
+    UPDATE "users"
+    SET "hair_color" = "hair_color_mapping"."original"
+    FROM "hair_color_mapping"
+    WHERE "users"."id" = "hair_color_mapping"."user_id"
+      AND "users"."hair_color" IS NOT DISTINCT FROM "hair_color_mapping"."mapped"
+    RETURNING "users"."id"
+)
+INSERT INTO "outbox" ("user_id")
+SELECT "id" FROM "restored";
+
+DROP TABLE "hair_color_mapping";
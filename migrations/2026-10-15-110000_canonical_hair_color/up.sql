@@ -0,0 +1,55 @@
+-- Rewrites every hair color in the canonical form that `HairColor` parses
+-- (see src/hair_color.rs), then enforces it.
+--
+-- Values that cannot be mapped are cleared. Every changed row is recorded in
+-- "hair_color_mapping" ("mapped" is NULL when the value was cleared), which
+-- is the report to review after migrating and what down.sql restores from.
+CREATE TABLE "hair_color_mapping"(
+                        "user_id" INTEGER PRIMARY KEY,
+                        "original" TEXT NOT NULL,
+                        "mapped" TEXT
+);
+
+WITH "cleaned" AS (
+    SELECT "id", "hair_color",
+           lower(btrim(regexp_replace("hair_color", '[[:space:]_-]+', ' ', 'g'))) AS "color"
+    FROM "users"
+    WHERE "hair_color" IS NOT NULL
+), "mapped" AS (
+    SELECT "id", "hair_color",
+           CASE
+               WHEN "color" ~ '^#[0-9a-f]{6}$' THEN "color"
+               WHEN "color" ~ '^#[0-9a-f]{3}$' THEN
+                   '#' || repeat(substr("color", 2, 1), 2)
+                       || repeat(substr("color", 3, 1), 2)
+                       || repeat(substr("color", 4, 1), 2)
+               WHEN "color" = 'blond' THEN 'blonde'
+               WHEN "color" = 'grey' THEN 'gray'
+               WHEN "color" = 'strawberry blond' THEN 'strawberry blonde'
+               WHEN "color" IN ('auburn', 'black', 'blonde', 'brown', 'chestnut', 'dark brown',
+                                'ginger', 'gray', 'light brown', 'platinum', 'red', 'silver',
+                                'strawberry blonde', 'white') THEN "color"
+           END AS "mapped"
+    FROM "cleaned"
+)
+INSERT INTO "hair_color_mapping" ("user_id", "original", "mapped")
+SELECT "id", "hair_color", "mapped"
+FROM "mapped"
+WHERE "mapped" IS DISTINCT FROM "hair_color";
+
+UPDATE "users"
+SET "hair_color" = "hair_color_mapping"."mapped"
+FROM "hair_color_mapping"
+WHERE "users"."id" = "hair_color_mapping"."user_id";
+
+-- the search index still holds the old values
+INSERT INTO "outbox" ("user_id")
+SELECT "user_id" FROM "hair_color_mapping";
+
+ALTER TABLE "users"
+    ADD CONSTRAINT "users_hair_color_canonical" CHECK (
+        "hair_color" ~ '^#[0-9a-f]{6}$'
+        OR "hair_color" IN ('auburn', 'black', 'blonde', 'brown', 'chestnut', 'dark brown',
+                            'ginger', 'gray', 'light brown', 'platinum', 'red', 'silver',
+                            'strawberry blonde', 'white')
+    );
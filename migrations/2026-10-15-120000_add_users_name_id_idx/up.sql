@@ -0,0 +1,3 @@
+-- Your SQL goes here
+-- serves `GET /users?order=name`, whose pages seek past the last (name, id)
+CREATE INDEX "users_name_id_idx" ON "users" ("name", "id");
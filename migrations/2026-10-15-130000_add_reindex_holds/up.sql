@@ -0,0 +1,7 @@
+-- Your SQL goes here
+-- a row per running reindex; the outbox worker claims nothing while one is
+-- unexpired, so writes made during the rebuild reach the swapped-in index
+CREATE TABLE "reindex_holds"(
+                        "staging_index" TEXT PRIMARY KEY,
+                        "expires_at" TIMESTAMPTZ NOT NULL
+);
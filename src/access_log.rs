@@ -0,0 +1,99 @@
+//! The per-request span and access log line, keyed by an `X-Request-Id` that
+//! is echoed in the response and in problem bodies so a support ticket can be
+//! matched to its log lines.
+
+use std::time::Instant;
+
+use axum::{
+    extract::MatchedPath,
+    http::{header, HeaderValue, Request},
+    middleware::Next,
+    response::Response,
+};
+use opentelemetry::propagation::TextMapPropagator as _;
+use opentelemetry_http::HeaderExtractor;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use tracing::Instrument;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+pub const X_REQUEST_ID: &str = "x-request-id";
+
+/// Longest caller-supplied request id that is kept rather than replaced.
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// The id of the current request, in the request extensions.
+#[derive(Clone, Debug)]
+pub struct RequestId(pub String);
+
+/// Uses the caller's id if it is short printable ASCII, so it cannot forge
+/// log lines, and a fresh UUID otherwise.
+fn request_id<B>(request: &Request<B>) -> String {
+    request
+        .headers()
+        .get(X_REQUEST_ID)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| {
+            (1..=MAX_REQUEST_ID_LEN).contains(&id.len()) && id.bytes().all(|b| b.is_ascii_graphic())
+        })
+        .map_or_else(|| uuid::Uuid::new_v4().to_string(), str::to_owned)
+}
+
+/// Assigns the request id, opens the `http.request` span (continuing the
+/// caller's trace from its W3C `traceparent` header) and logs one line per
+/// request once the response is ready.
+pub async fn access_log<B>(mut request: Request<B>, next: Next<B>) -> Response {
+    let started = Instant::now();
+    let id = request_id(&request);
+    let header_value = HeaderValue::from_str(&id).expect("request ids are printable ASCII");
+    request
+        .headers_mut()
+        .insert(X_REQUEST_ID, header_value.clone());
+    request.extensions_mut().insert(RequestId(id.clone()));
+
+    let method = request.method().clone();
+    let user_agent = request
+        .headers()
+        .get(header::USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_owned();
+    let span = tracing::info_span!(
+        "http.request",
+        otel.name = %method,
+        otel.kind = "server",
+        otel.status_code = tracing::field::Empty,
+        request_id = %id,
+        http.method = %method,
+        http.route = tracing::field::Empty,
+        http.status_code = tracing::field::Empty,
+        http.user_agent = %user_agent,
+        latency_ms = tracing::field::Empty,
+    );
+    span.set_parent(TraceContextPropagator::new().extract(&HeaderExtractor(request.headers())));
+
+    let mut response = next.run(request).instrument(span.clone()).await;
+    let status = response.status();
+    let latency_ms = started.elapsed().as_secs_f64() * 1000.0;
+    span.record("http.status_code", status.as_u16());
+    span.record("latency_ms", latency_ms);
+    if status.is_server_error() {
+        span.record("otel.status_code", "ERROR");
+    }
+    response.headers_mut().insert(X_REQUEST_ID, header_value);
+    span.in_scope(|| tracing::info!(status = status.as_u16(), latency_ms, "request completed"));
+    response
+}
+
+/// Adds the matched route pattern (e.g. `/users/:id`) to the request span.
+/// Install as a route layer, which runs after routing.
+pub async fn record_route<B>(request: Request<B>, next: Next<B>) -> Response {
+    if let Some(route) = request.extensions().get::<MatchedPath>() {
+        let span = tracing::Span::current();
+        span.record("http.route", route.as_str());
+        span.record(
+            "otel.name",
+            format_args!("{} {}", request.method(), route.as_str()),
+        );
+    }
+    next.run(request).await
+}
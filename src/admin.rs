@@ -0,0 +1,70 @@
+//! Operational endpoints under `/admin`, only mounted when an admin token is
+//! configured and only answering requests that present it.
+
+use std::sync::Arc;
+
+use axum::{
+    async_trait,
+    extract::{FromRef, FromRequestParts},
+    http::{header, request::Parts},
+};
+
+use crate::{error::AppError, extract::Json, logging::LogFilter, secret::Secret};
+
+pub struct Admin {
+    token: Secret<String>,
+    log_filter: LogFilter,
+}
+
+impl Admin {
+    pub fn new(token: Secret<String>, log_filter: LogFilter) -> Self {
+        Admin { token, log_filter }
+    }
+}
+
+/// Rejects the request unless it carries `Authorization: Bearer <admin token>`.
+pub struct AdminAuth(pub Arc<Admin>);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for AdminAuth
+where
+    S: Send + Sync,
+    Option<Arc<Admin>>: FromRef<S>,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let admin = Option::<Arc<Admin>>::from_ref(state).ok_or(AppError::Unauthorized)?;
+        let token = parts
+            .headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or(AppError::Unauthorized)?;
+        if !admin.token.matches(token) {
+            return Err(AppError::Unauthorized);
+        }
+        Ok(AdminAuth(admin))
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct LogFilterBody {
+    /// `tracing` filter directives, e.g. `info,issue=debug`
+    pub filter: String,
+}
+
+pub async fn get_log_filter(AdminAuth(admin): AdminAuth) -> Json<LogFilterBody> {
+    Json(LogFilterBody {
+        filter: admin.log_filter.directives(),
+    })
+}
+
+pub async fn put_log_filter(
+    AdminAuth(admin): AdminAuth,
+    Json(body): Json<LogFilterBody>,
+) -> Result<Json<LogFilterBody>, AppError> {
+    admin.log_filter.set(&body.filter)?;
+    tracing::info!(filter = %body.filter, "log filter changed");
+    Ok(Json(body))
+}
@@ -0,0 +1,124 @@
+//! Where search documents live. Handlers, the outbox worker and `reindex`
+//! only talk to a [`SearchBackend`]; which one is chosen by configuration.
+
+mod instrumented;
+mod meilisearch;
+mod memory;
+mod tracked;
+
+use std::{collections::HashMap, future::Future, time::Duration};
+
+use axum::async_trait;
+
+use crate::{
+    models::User,
+    search::{IndexStatus, SearchSort},
+};
+
+pub use self::{
+    instrumented::InstrumentedBackend, meilisearch::MeilisearchBackend, memory::InMemoryBackend,
+    tracked::TrackedBackend,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum SearchError {
+    #[error(transparent)]
+    Meilisearch(#[from] meilisearch_sdk::errors::Error),
+    #[error("task {0} did not finish in time")]
+    Timeout(u32),
+    #[error("task {uid} failed: {message}")]
+    TaskFailed { uid: u32, message: String },
+    #[error("search backend did not answer within {0:?}")]
+    Unresponsive(Duration),
+}
+
+/// Fails `call` with [`SearchError::Unresponsive`] once `limit` has passed.
+/// Backends bound how long they wait for a task, but not the HTTP requests
+/// underneath, and a hung server never answers those.
+pub async fn bounded<T>(
+    limit: Duration,
+    call: impl Future<Output = Result<T, SearchError>>,
+) -> Result<T, SearchError> {
+    tokio::time::timeout(limit, call)
+        .await
+        .unwrap_or(Err(SearchError::Unresponsive(limit)))
+}
+
+/// Last known state of an indexing task.
+#[derive(Clone, Debug)]
+pub struct TaskState {
+    pub uid: u32,
+    pub status: IndexStatus,
+    pub error: Option<String>,
+}
+
+pub struct SearchRequest<'a> {
+    pub query: &'a str,
+    pub hair_color: Option<&'a str>,
+    pub sort: Option<SearchSort>,
+    pub page: usize,
+    pub hits_per_page: usize,
+}
+
+pub struct SearchHit {
+    pub id: i32,
+    /// Matched attributes with the query terms wrapped in `<em>` tags.
+    pub highlight: HashMap<String, String>,
+}
+
+pub struct SearchPage {
+    pub hits: Vec<SearchHit>,
+    pub total_hits: usize,
+    pub total_pages: usize,
+}
+
+/// A search index of users. Writes are asynchronous: they return the uid of
+/// a task that can be polled or waited on.
+#[async_trait]
+pub trait SearchBackend: Send + Sync {
+    /// Applies the settings search relies on (searchable, filterable and
+    /// sortable attributes).
+    async fn configure(&self, index: &str) -> Result<u32, SearchError>;
+
+    async fn upsert(&self, index: &str, users: &[User]) -> Result<u32, SearchError>;
+
+    async fn delete(&self, index: &str, ids: &[i32]) -> Result<u32, SearchError>;
+
+    async fn search(
+        &self,
+        index: &str,
+        request: &SearchRequest<'_>,
+    ) -> Result<SearchPage, SearchError>;
+
+    /// Fails if the backend cannot currently serve requests.
+    async fn health(&self) -> Result<(), SearchError>;
+
+    /// Looks up a task, returning `None` if the backend does not know it.
+    async fn task(&self, uid: u32) -> Result<Option<TaskState>, SearchError>;
+
+    /// Waits until the task has finished, or fails with
+    /// [`SearchError::Timeout`].
+    async fn wait(&self, uid: u32, timeout: Duration) -> Result<TaskState, SearchError>;
+
+    /// Creates `index`, doing nothing if it already exists.
+    async fn ensure_index(&self, index: &str, timeout: Duration) -> Result<(), SearchError>;
+
+    async fn create_index(&self, index: &str) -> Result<u32, SearchError>;
+
+    async fn delete_index(&self, index: &str) -> Result<u32, SearchError>;
+
+    /// Exchanges the documents and settings of two indexes.
+    async fn swap_indexes(&self, a: &str, b: &str) -> Result<u32, SearchError>;
+
+    /// Waits for the task and turns a failed task into an error.
+    async fn wait_for_success(&self, uid: u32, timeout: Duration) -> Result<(), SearchError> {
+        let task = self.wait(uid, timeout).await?;
+        if task.status == IndexStatus::Failed {
+            return Err(SearchError::TaskFailed {
+                uid,
+                message: task.error.unwrap_or_default(),
+            });
+        }
+        Ok(())
+    }
+}
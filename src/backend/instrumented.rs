@@ -0,0 +1,96 @@
+use std::{future::Future, sync::Arc, time::Duration, time::Instant};
+
+use axum::async_trait;
+use tracing::Instrument;
+
+use super::{SearchBackend, SearchError, SearchPage, SearchRequest, TaskState};
+use crate::{metrics::Metrics, models::User};
+
+/// Wraps a backend and records the outcome and duration of every call, each
+/// in its own span.
+pub struct InstrumentedBackend {
+    inner: Arc<dyn SearchBackend>,
+    metrics: Arc<Metrics>,
+}
+
+impl InstrumentedBackend {
+    pub fn new(inner: Arc<dyn SearchBackend>, metrics: Arc<Metrics>) -> Self {
+        InstrumentedBackend { inner, metrics }
+    }
+
+    async fn call<T>(
+        &self,
+        operation: &'static str,
+        call: impl Future<Output = Result<T, SearchError>>,
+    ) -> Result<T, SearchError> {
+        let span = tracing::info_span!(
+            "search.call",
+            otel.name = operation,
+            otel.kind = "client",
+            otel.status_code = tracing::field::Empty,
+        );
+        let started = Instant::now();
+        let result = call.instrument(span.clone()).await;
+        if result.is_err() {
+            span.record("otel.status_code", "ERROR");
+        }
+        self.metrics
+            .observe_search(operation, result.is_ok(), started);
+        result
+    }
+}
+
+#[async_trait]
+impl SearchBackend for InstrumentedBackend {
+    async fn configure(&self, index: &str) -> Result<u32, SearchError> {
+        self.call("configure", self.inner.configure(index)).await
+    }
+
+    async fn upsert(&self, index: &str, users: &[User]) -> Result<u32, SearchError> {
+        self.call("upsert", self.inner.upsert(index, users)).await
+    }
+
+    async fn delete(&self, index: &str, ids: &[i32]) -> Result<u32, SearchError> {
+        self.call("delete", self.inner.delete(index, ids)).await
+    }
+
+    async fn search(
+        &self,
+        index: &str,
+        request: &SearchRequest<'_>,
+    ) -> Result<SearchPage, SearchError> {
+        self.call("search", self.inner.search(index, request)).await
+    }
+
+    async fn health(&self) -> Result<(), SearchError> {
+        self.call("health", self.inner.health()).await
+    }
+
+    async fn task(&self, uid: u32) -> Result<Option<TaskState>, SearchError> {
+        self.call("task", self.inner.task(uid)).await
+    }
+
+    async fn wait(&self, uid: u32, timeout: Duration) -> Result<TaskState, SearchError> {
+        self.call("wait", self.inner.wait(uid, timeout)).await
+    }
+
+    async fn ensure_index(&self, index: &str, timeout: Duration) -> Result<(), SearchError> {
+        self.call("ensure_index", self.inner.ensure_index(index, timeout))
+            .await
+    }
+
+    async fn create_index(&self, index: &str) -> Result<u32, SearchError> {
+        self.call("create_index", self.inner.create_index(index))
+            .await
+    }
+
+    async fn delete_index(&self, index: &str) -> Result<u32, SearchError> {
+        self.call("delete_index", self.inner.delete_index(index))
+            .await
+    }
+
+    async fn swap_indexes(&self, a: &str, b: &str) -> Result<u32, SearchError> {
+        self.call("swap_indexes", self.inner.swap_indexes(a, b))
+            .await
+    }
+}
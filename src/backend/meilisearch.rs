@@ -0,0 +1,208 @@
+use std::time::Duration;
+
+use axum::async_trait;
+use meilisearch_sdk::{
+    client::{Client, SwapIndexes},
+    errors::{Error, ErrorCode},
+    search::Selectors,
+    settings::Settings,
+    tasks::Task,
+};
+
+use super::{SearchBackend, SearchError, SearchHit, SearchPage, SearchRequest, TaskState};
+use crate::search::{highlighted_fields, IndexStatus, SearchSort};
+
+/// Talks to a Meilisearch server through `meilisearch_sdk`.
+#[derive(Clone)]
+pub struct MeilisearchBackend {
+    client: Client,
+}
+
+impl MeilisearchBackend {
+    pub fn new(client: Client) -> Self {
+        MeilisearchBackend { client }
+    }
+}
+
+// `Client::get_task` only takes `AsRef<u32>`, which bare integers don't implement
+struct TaskUid(u32);
+
+impl AsRef<u32> for TaskUid {
+    fn as_ref(&self) -> &u32 {
+        &self.0
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct IndexedId {
+    id: i32,
+}
+
+impl From<Task> for TaskState {
+    fn from(task: Task) -> Self {
+        let uid = task.get_uid();
+        match task {
+            Task::Enqueued { .. } => TaskState {
+                uid,
+                status: IndexStatus::Enqueued,
+                error: None,
+            },
+            Task::Processing { .. } => TaskState {
+                uid,
+                status: IndexStatus::Processing,
+                error: None,
+            },
+            Task::Succeeded { .. } => TaskState {
+                uid,
+                status: IndexStatus::Succeeded,
+                error: None,
+            },
+            Task::Failed { content } => TaskState {
+                uid,
+                status: IndexStatus::Failed,
+                error: Some(content.error.error_message),
+            },
+        }
+    }
+}
+
+fn sort_rule(sort: SearchSort) -> &'static [&'static str] {
+    match sort {
+        SearchSort::IdAsc => &["id:asc"],
+        SearchSort::IdDesc => &["id:desc"],
+        SearchSort::NameAsc => &["name:asc"],
+        SearchSort::NameDesc => &["name:desc"],
+    }
+}
+
+// Meilisearch filter strings are double-quoted; escape the user input accordingly
+fn quote_filter_value(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[async_trait]
+impl SearchBackend for MeilisearchBackend {
+    async fn configure(&self, index: &str) -> Result<u32, SearchError> {
+        // filtering and sorting need to be declared up front in Meilisearch
+        let settings = Settings::new()
+            .with_searchable_attributes(["name", "hair_color"])
+            .with_filterable_attributes(["hair_color"])
+            .with_sortable_attributes(["id", "name"]);
+        let task = self.client.index(index).set_settings(&settings).await?;
+        Ok(task.get_task_uid())
+    }
+
+    async fn upsert(&self, index: &str, users: &[crate::models::User]) -> Result<u32, SearchError> {
+        let task = self
+            .client
+            .index(index)
+            .add_or_replace(users, Some("id"))
+            .await?;
+        Ok(task.get_task_uid())
+    }
+
+    async fn delete(&self, index: &str, ids: &[i32]) -> Result<u32, SearchError> {
+        let task = self.client.index(index).delete_documents(ids).await?;
+        Ok(task.get_task_uid())
+    }
+
+    async fn search(
+        &self,
+        index: &str,
+        request: &SearchRequest<'_>,
+    ) -> Result<SearchPage, SearchError> {
+        let filter = request
+            .hair_color
+            .map(|hair_color| format!("hair_color = {}", quote_filter_value(hair_color)));
+
+        let index = self.client.index(index);
+        let mut query = index.search();
+        query
+            .with_query(request.query)
+            .with_page(request.page)
+            .with_hits_per_page(request.hits_per_page)
+            .with_attributes_to_highlight(Selectors::Some(&["name", "hair_color"]));
+        if let Some(filter) = &filter {
+            query.with_filter(filter);
+        }
+        if let Some(sort) = request.sort {
+            query.with_sort(sort_rule(sort));
+        }
+        let results = index.execute_query::<IndexedId>(&query).await?;
+
+        let hits: Vec<SearchHit> = results
+            .hits
+            .into_iter()
+            .map(|hit| SearchHit {
+                id: hit.result.id,
+                highlight: highlighted_fields(hit.formatted_result.unwrap_or_default()),
+            })
+            .collect();
+        Ok(SearchPage {
+            total_hits: results.total_hits.unwrap_or(hits.len()),
+            total_pages: results.total_pages.unwrap_or(0),
+            hits,
+        })
+    }
+
+    async fn health(&self) -> Result<(), SearchError> {
+        self.client.health().await?;
+        Ok(())
+    }
+
+    async fn task(&self, uid: u32) -> Result<Option<TaskState>, SearchError> {
+        match self.client.get_task(TaskUid(uid)).await {
+            Ok(task) => Ok(Some(task.into())),
+            Err(Error::Meilisearch(err)) if err.error_code == ErrorCode::TaskNotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn wait(&self, uid: u32, timeout: Duration) -> Result<TaskState, SearchError> {
+        match self
+            .client
+            .wait_for_task(TaskUid(uid), None, Some(timeout))
+            .await
+        {
+            Ok(task) => Ok(task.into()),
+            Err(Error::Timeout) => Err(SearchError::Timeout(uid)),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn ensure_index(&self, index: &str, timeout: Duration) -> Result<(), SearchError> {
+        let task = self.client.create_index(index, Some("id")).await?;
+        // the task fails when the index exists, which is fine here
+        match self.client.wait_for_task(&task, None, Some(timeout)).await {
+            Ok(Task::Failed { content })
+                if content.error.error_code != ErrorCode::IndexAlreadyExists =>
+            {
+                Err(SearchError::TaskFailed {
+                    uid: content.task.uid,
+                    message: content.error.error_message,
+                })
+            }
+            Ok(_) => Ok(()),
+            Err(Error::Timeout) => Err(SearchError::Timeout(task.get_task_uid())),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn create_index(&self, index: &str) -> Result<u32, SearchError> {
+        let task = self.client.create_index(index, Some("id")).await?;
+        Ok(task.get_task_uid())
+    }
+
+    async fn delete_index(&self, index: &str) -> Result<u32, SearchError> {
+        let task = self.client.delete_index(index).await?;
+        Ok(task.get_task_uid())
+    }
+
+    async fn swap_indexes(&self, a: &str, b: &str) -> Result<u32, SearchError> {
+        let swap = SwapIndexes {
+            indexes: (a.to_owned(), b.to_owned()),
+        };
+        let task = self.client.swap_indexes([&swap]).await?;
+        Ok(task.get_task_uid())
+    }
+}
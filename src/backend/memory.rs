@@ -0,0 +1,254 @@
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    sync::Mutex,
+    time::Duration,
+};
+
+use axum::async_trait;
+
+use super::{SearchBackend, SearchError, SearchHit, SearchPage, SearchRequest, TaskState};
+use crate::{
+    hair_color::HairColor,
+    models::User,
+    search::{IndexStatus, SearchSort},
+};
+
+/// How many finished tasks [`InMemoryBackend`] remembers. Older ones are
+/// forgotten, as Meilisearch prunes its own task history.
+const TASK_HISTORY: usize = 1000;
+
+/// Keeps indexes in process memory, for tests and for working offline.
+///
+/// Every task has already succeeded by the time its uid is returned, and
+/// only the last [`TASK_HISTORY`] tasks can be looked up. Matching
+/// is a case-insensitive substring test of each query word against `name`
+/// and `hair_color`, with no ranking or typo tolerance.
+#[derive(Default)]
+pub struct InMemoryBackend {
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    indexes: HashMap<String, BTreeMap<i32, User>>,
+    /// The most recent tasks, oldest first
+    tasks: VecDeque<TaskState>,
+    next_uid: u32,
+}
+
+impl State {
+    fn finish(&mut self, error: Option<String>) -> u32 {
+        let uid = self.next_uid;
+        self.next_uid = self.next_uid.wrapping_add(1);
+        let status = match error {
+            Some(_) => IndexStatus::Failed,
+            None => IndexStatus::Succeeded,
+        };
+        if self.tasks.len() == TASK_HISTORY {
+            self.tasks.pop_front();
+        }
+        self.tasks.push_back(TaskState { uid, status, error });
+        uid
+    }
+}
+
+impl InMemoryBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Documents currently stored in `index`, ordered by id.
+    pub fn documents(&self, index: &str) -> Vec<User> {
+        let state = self.state.lock().unwrap();
+        state
+            .indexes
+            .get(index)
+            .map(|documents| documents.values().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Uids of every index, sorted.
+    pub fn indexes(&self) -> Vec<String> {
+        let state = self.state.lock().unwrap();
+        let mut indexes: Vec<String> = state.indexes.keys().cloned().collect();
+        indexes.sort();
+        indexes
+    }
+}
+
+fn highlight(text: &str, terms: &[String]) -> Option<String> {
+    let lower = text.to_lowercase();
+    let mut out = String::new();
+    let mut rest = 0;
+    for (start, _) in lower.char_indices() {
+        if start < rest {
+            continue;
+        }
+        if let Some(term) = terms
+            .iter()
+            .find(|term| lower[start..].starts_with(term.as_str()))
+        {
+            // lowercasing can change byte lengths; only highlight where it didn't
+            let Some(matched) = text.get(start..start + term.len()) else {
+                continue;
+            };
+            out.push_str(&text[rest..start]);
+            out.push_str("<em>");
+            out.push_str(matched);
+            out.push_str("</em>");
+            rest = start + term.len();
+        }
+    }
+    if rest == 0 {
+        return None;
+    }
+    out.push_str(&text[rest..]);
+    Some(out)
+}
+
+fn matches(user: &User, terms: &[String]) -> bool {
+    terms.iter().all(|term| {
+        user.name.to_lowercase().contains(term.as_str())
+            || user
+                .hair_color
+                .as_ref()
+                .is_some_and(|hair_color| hair_color.as_str().contains(term.as_str()))
+    })
+}
+
+#[async_trait]
+impl SearchBackend for InMemoryBackend {
+    async fn configure(&self, index: &str) -> Result<u32, SearchError> {
+        let mut state = self.state.lock().unwrap();
+        state.indexes.entry(index.to_owned()).or_default();
+        Ok(state.finish(None))
+    }
+
+    async fn upsert(&self, index: &str, users: &[User]) -> Result<u32, SearchError> {
+        let mut state = self.state.lock().unwrap();
+        let documents = state.indexes.entry(index.to_owned()).or_default();
+        for user in users {
+            documents.insert(user.id, user.clone());
+        }
+        Ok(state.finish(None))
+    }
+
+    async fn delete(&self, index: &str, ids: &[i32]) -> Result<u32, SearchError> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(documents) = state.indexes.get_mut(index) {
+            for id in ids {
+                documents.remove(id);
+            }
+        }
+        Ok(state.finish(None))
+    }
+
+    async fn search(
+        &self,
+        index: &str,
+        request: &SearchRequest<'_>,
+    ) -> Result<SearchPage, SearchError> {
+        let state = self.state.lock().unwrap();
+        let terms: Vec<String> = request
+            .query
+            .split_whitespace()
+            .map(str::to_lowercase)
+            .collect();
+        let mut found: Vec<&User> = state
+            .indexes
+            .get(index)
+            .into_iter()
+            .flat_map(BTreeMap::values)
+            .filter(|user| matches(user, &terms))
+            .filter(|user| {
+                request
+                    .hair_color
+                    .is_none_or(|hair_color| user.hair_color.as_ref().map(HairColor::as_str) == Some(hair_color))
+            })
+            .collect();
+        match request.sort {
+            Some(SearchSort::IdAsc) | None => {}
+            Some(SearchSort::IdDesc) => found.reverse(),
+            Some(SearchSort::NameAsc) => found.sort_by(|a, b| a.name.cmp(&b.name)),
+            Some(SearchSort::NameDesc) => found.sort_by(|a, b| b.name.cmp(&a.name)),
+        }
+
+        let total_hits = found.len();
+        let hits = found
+            .into_iter()
+            .skip(
+                request
+                    .page
+                    .saturating_sub(1)
+                    .saturating_mul(request.hits_per_page),
+            )
+            .take(request.hits_per_page)
+            .map(|user| {
+                let fields = [
+                    ("name", Some(user.name.as_str())),
+                    ("hair_color", user.hair_color.as_ref().map(HairColor::as_str)),
+                ];
+                let highlight = fields
+                    .into_iter()
+                    .filter_map(|(field, value)| {
+                        Some((field.to_owned(), highlight(value?, &terms)?))
+                    })
+                    .collect();
+                SearchHit {
+                    id: user.id,
+                    highlight,
+                }
+            })
+            .collect();
+        Ok(SearchPage {
+            hits,
+            total_hits,
+            total_pages: total_hits.div_ceil(request.hits_per_page),
+        })
+    }
+
+    async fn health(&self) -> Result<(), SearchError> {
+        Ok(())
+    }
+
+    async fn task(&self, uid: u32) -> Result<Option<TaskState>, SearchError> {
+        let state = self.state.lock().unwrap();
+        Ok(state.tasks.iter().rev().find(|task| task.uid == uid).cloned())
+    }
+
+    async fn wait(&self, uid: u32, _timeout: Duration) -> Result<TaskState, SearchError> {
+        // tasks finish before their uid is handed out, so an unknown uid,
+        // whether never issued or already forgotten, will never complete
+        self.task(uid).await?.ok_or(SearchError::Timeout(uid))
+    }
+
+    async fn ensure_index(&self, index: &str, _timeout: Duration) -> Result<(), SearchError> {
+        let mut state = self.state.lock().unwrap();
+        state.indexes.entry(index.to_owned()).or_default();
+        Ok(())
+    }
+
+    async fn create_index(&self, index: &str) -> Result<u32, SearchError> {
+        let mut state = self.state.lock().unwrap();
+        if state.indexes.contains_key(index) {
+            return Ok(state.finish(Some(format!("Index `{index}` already exists."))));
+        }
+        state.indexes.insert(index.to_owned(), BTreeMap::new());
+        Ok(state.finish(None))
+    }
+
+    async fn delete_index(&self, index: &str) -> Result<u32, SearchError> {
+        let mut state = self.state.lock().unwrap();
+        state.indexes.remove(index);
+        Ok(state.finish(None))
+    }
+
+    async fn swap_indexes(&self, a: &str, b: &str) -> Result<u32, SearchError> {
+        let mut state = self.state.lock().unwrap();
+        let documents_a = state.indexes.remove(a).unwrap_or_default();
+        let documents_b = state.indexes.remove(b).unwrap_or_default();
+        state.indexes.insert(a.to_owned(), documents_b);
+        state.indexes.insert(b.to_owned(), documents_a);
+        Ok(state.finish(None))
+    }
+}
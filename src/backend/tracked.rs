@@ -0,0 +1,94 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use axum::async_trait;
+
+use super::{SearchBackend, SearchError, SearchPage, SearchRequest, TaskState};
+use crate::models::User;
+
+/// Wraps a backend and remembers the newest task submitted through it, so
+/// shutdown can wait for indexing the process started.
+pub struct TrackedBackend {
+    inner: Arc<dyn SearchBackend>,
+    last_task: Mutex<Option<u32>>,
+}
+
+impl TrackedBackend {
+    pub fn new(inner: Arc<dyn SearchBackend>) -> Self {
+        TrackedBackend {
+            inner,
+            last_task: Mutex::new(None),
+        }
+    }
+
+    fn track(&self, uid: Result<u32, SearchError>) -> Result<u32, SearchError> {
+        if let Ok(uid) = uid {
+            let mut last_task = self.last_task.lock().unwrap();
+            *last_task = Some(last_task.map_or(uid, |last| last.max(uid)));
+        }
+        uid
+    }
+
+    /// Waits for the newest submitted task. Meilisearch processes tasks in
+    /// uid order, so every earlier task has finished once this one has.
+    pub async fn wait_for_pending(&self, timeout: Duration) -> Result<(), SearchError> {
+        let last_task = *self.last_task.lock().unwrap();
+        match last_task {
+            Some(uid) => self.inner.wait(uid, timeout).await.map(|_| ()),
+            None => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl SearchBackend for TrackedBackend {
+    async fn configure(&self, index: &str) -> Result<u32, SearchError> {
+        self.track(self.inner.configure(index).await)
+    }
+
+    async fn upsert(&self, index: &str, users: &[User]) -> Result<u32, SearchError> {
+        self.track(self.inner.upsert(index, users).await)
+    }
+
+    async fn delete(&self, index: &str, ids: &[i32]) -> Result<u32, SearchError> {
+        self.track(self.inner.delete(index, ids).await)
+    }
+
+    async fn search(
+        &self,
+        index: &str,
+        request: &SearchRequest<'_>,
+    ) -> Result<SearchPage, SearchError> {
+        self.inner.search(index, request).await
+    }
+
+    async fn health(&self) -> Result<(), SearchError> {
+        self.inner.health().await
+    }
+
+    async fn task(&self, uid: u32) -> Result<Option<TaskState>, SearchError> {
+        self.inner.task(uid).await
+    }
+
+    async fn wait(&self, uid: u32, timeout: Duration) -> Result<TaskState, SearchError> {
+        self.inner.wait(uid, timeout).await
+    }
+
+    async fn ensure_index(&self, index: &str, timeout: Duration) -> Result<(), SearchError> {
+        self.inner.ensure_index(index, timeout).await
+    }
+
+    async fn create_index(&self, index: &str) -> Result<u32, SearchError> {
+        self.track(self.inner.create_index(index).await)
+    }
+
+    async fn delete_index(&self, index: &str) -> Result<u32, SearchError> {
+        self.track(self.inner.delete_index(index).await)
+    }
+
+    async fn swap_indexes(&self, a: &str, b: &str) -> Result<u32, SearchError> {
+        self.track(self.inner.swap_indexes(a, b).await)
+    }
+}
@@ -0,0 +1,588 @@
+use std::{
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use crate::{
+    backend::{InMemoryBackend, MeilisearchBackend, SearchBackend},
+    secret::{self, Secret},
+};
+
+const DEFAULT_BIND_ADDRESS: ([u8; 4], u16) = ([127, 0, 0, 1], 3000);
+const DEFAULT_MAX_CONNECTIONS: u32 = 10;
+const DEFAULT_CONNECTION_TIMEOUT_SECS: u64 = 30;
+const DEFAULT_MEILISEARCH_URL: &str = "http://localhost:7700";
+const DEFAULT_SHUTDOWN_TIMEOUT_SECS: u64 = 30;
+const DEFAULT_SERVICE_NAME: &str = "issue";
+const DEFAULT_LOG_FILTER: &str = "warn,issue=info";
+/// HMAC-SHA256 keys shorter than its output weaken the cursor signatures.
+const MIN_CURSOR_SECRET_LEN: usize = 32;
+
+/// Settings that can be given on the command line or through the environment.
+///
+/// These take precedence over the config file, which in turn overrides the
+/// built-in defaults.
+#[derive(clap::Args, Debug, Default)]
+pub struct ConfigArgs {
+    /// Path to a TOML config file
+    #[arg(long = "config", env = "CONFIG_FILE", global = true)]
+    pub config_file: Option<PathBuf>,
+    /// Address the HTTP server listens on
+    #[arg(long, env = "BIND_ADDRESS", global = true)]
+    pub bind_address: Option<SocketAddr>,
+    /// Seconds to let in-flight requests and search indexing finish after
+    /// SIGTERM or Ctrl-C
+    #[arg(long, env = "SHUTDOWN_TIMEOUT_SECS", global = true)]
+    pub shutdown_timeout_secs: Option<u64>,
+    /// Postgres connection URL
+    #[arg(long, env = "DATABASE_URL", hide_env_values = true, global = true)]
+    pub database_url: Option<Secret<String>>,
+    /// File containing the Postgres connection URL
+    #[arg(long, env = "DATABASE_URL_FILE", global = true)]
+    pub database_url_file: Option<PathBuf>,
+    /// Postgres password, replacing any password in the connection URL
+    #[arg(long, env = "DATABASE_PASSWORD", hide_env_values = true, global = true)]
+    pub database_password: Option<Secret<String>>,
+    /// File containing the Postgres password
+    #[arg(long, env = "DATABASE_PASSWORD_FILE", global = true)]
+    pub database_password_file: Option<PathBuf>,
+    /// Maximum number of pooled database connections
+    #[arg(long, env = "DATABASE_MAX_CONNECTIONS", global = true)]
+    pub database_max_connections: Option<u32>,
+    /// Idle database connections the pool keeps open
+    #[arg(long, env = "DATABASE_MIN_IDLE", global = true)]
+    pub database_min_idle: Option<u32>,
+    /// Seconds to wait for a pooled database connection
+    #[arg(long, env = "DATABASE_CONNECTION_TIMEOUT_SECS", global = true)]
+    pub database_connection_timeout_secs: Option<u64>,
+    /// Apply pending schema migrations before serving
+    #[arg(
+        long,
+        env = "RUN_MIGRATIONS",
+        num_args = 0..=1,
+        default_missing_value = "true",
+        require_equals = true,
+        global = true
+    )]
+    pub run_migrations: Option<bool>,
+    /// Base URL of the Meilisearch server
+    #[arg(long, env = "MEILISEARCH_URL", global = true)]
+    pub meilisearch_url: Option<String>,
+    /// Meilisearch API key
+    #[arg(
+        long,
+        env = "MEILISEARCH_API_KEY",
+        hide_env_values = true,
+        global = true
+    )]
+    pub meilisearch_api_key: Option<Secret<String>>,
+    /// File containing the Meilisearch API key
+    #[arg(long, env = "MEILISEARCH_API_KEY_FILE", global = true)]
+    pub meilisearch_api_key_file: Option<PathBuf>,
+    /// Where the users search index lives
+    #[arg(long, env = "SEARCH_BACKEND", global = true)]
+    pub search_backend: Option<SearchBackendKind>,
+    /// OTLP/HTTP collector to export traces to, e.g. `http://localhost:4318`
+    #[arg(long, env = "OTEL_EXPORTER_OTLP_ENDPOINT", global = true)]
+    pub otlp_endpoint: Option<String>,
+    /// `service.name` reported with exported traces
+    #[arg(long, env = "OTEL_SERVICE_NAME", global = true)]
+    pub service_name: Option<String>,
+    /// `tracing` filter directives, e.g. `info,issue=debug`
+    #[arg(long, env = "RUST_LOG", global = true)]
+    pub log_filter: Option<String>,
+    /// How log lines are written
+    #[arg(long, env = "LOG_FORMAT", global = true)]
+    pub log_format: Option<LogFormat>,
+    /// Bearer token for the `/admin` endpoints, which are disabled without one
+    #[arg(long, env = "ADMIN_TOKEN", hide_env_values = true, global = true)]
+    pub admin_token: Option<Secret<String>>,
+    /// File containing the admin token
+    #[arg(long, env = "ADMIN_TOKEN_FILE", global = true)]
+    pub admin_token_file: Option<PathBuf>,
+    /// Key signing `GET /users` pagination cursors; share it between replicas
+    #[arg(long, env = "CURSOR_SECRET", hide_env_values = true, global = true)]
+    pub cursor_secret: Option<Secret<String>>,
+    /// File containing the cursor signing key
+    #[arg(long, env = "CURSOR_SECRET_FILE", global = true)]
+    pub cursor_secret_file: Option<PathBuf>,
+}
+
+#[derive(clap::ValueEnum, serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchBackendKind {
+    /// A Meilisearch server
+    #[default]
+    Meilisearch,
+    /// An index in process memory, rebuilt from Postgres on every start
+    Memory,
+}
+
+#[derive(clap::ValueEnum, serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human-readable lines, coloured on a terminal
+    #[default]
+    Pretty,
+    /// One JSON object per line, for log collectors
+    Json,
+}
+
+/// Layout of the TOML config file. Every key is optional.
+#[derive(serde::Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+struct FileConfig {
+    bind_address: Option<SocketAddr>,
+    shutdown_timeout_secs: Option<u64>,
+    log_filter: Option<String>,
+    log_format: Option<LogFormat>,
+    admin_token: Option<Secret<String>>,
+    admin_token_file: Option<PathBuf>,
+    cursor_secret: Option<Secret<String>>,
+    cursor_secret_file: Option<PathBuf>,
+    #[serde(default)]
+    database: FileDatabaseConfig,
+    #[serde(default)]
+    meilisearch: FileMeilisearchConfig,
+    #[serde(default)]
+    search: FileSearchConfig,
+    #[serde(default)]
+    telemetry: FileTelemetryConfig,
+}
+
+#[derive(serde::Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+struct FileDatabaseConfig {
+    url: Option<Secret<String>>,
+    url_file: Option<PathBuf>,
+    password: Option<Secret<String>>,
+    password_file: Option<PathBuf>,
+    max_connections: Option<u32>,
+    min_idle: Option<u32>,
+    connection_timeout_secs: Option<u64>,
+    run_migrations: Option<bool>,
+}
+
+#[derive(serde::Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+struct FileMeilisearchConfig {
+    url: Option<String>,
+    api_key: Option<Secret<String>>,
+    api_key_file: Option<PathBuf>,
+}
+
+#[derive(serde::Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+struct FileSearchConfig {
+    backend: Option<SearchBackendKind>,
+}
+
+#[derive(serde::Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+struct FileTelemetryConfig {
+    otlp_endpoint: Option<String>,
+    service_name: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct Config {
+    pub bind_address: SocketAddr,
+    pub shutdown_timeout: Duration,
+    pub log_filter: String,
+    pub log_format: LogFormat,
+    pub admin_token: Option<Secret<String>>,
+    /// Without one, each process signs cursors with a random key of its own.
+    pub cursor_secret: Option<Secret<String>>,
+    pub database: DatabaseConfig,
+    pub meilisearch: MeilisearchConfig,
+    pub search: SearchConfig,
+    pub telemetry: TelemetryConfig,
+}
+
+#[derive(Debug)]
+pub struct DatabaseConfig {
+    pub url: Secret<String>,
+    pub max_connections: u32,
+    pub min_idle: Option<u32>,
+    pub connection_timeout: Duration,
+    /// Whether `serve` applies pending migrations instead of refusing to
+    /// start on an outdated schema.
+    pub run_migrations: bool,
+}
+
+#[derive(Debug)]
+pub struct MeilisearchConfig {
+    pub url: String,
+    pub api_key: Option<Secret<String>>,
+}
+
+#[derive(Debug)]
+pub struct SearchConfig {
+    pub backend: SearchBackendKind,
+}
+
+#[derive(Debug)]
+pub struct TelemetryConfig {
+    /// Base URL of an OTLP/HTTP collector; traces are not exported without one.
+    pub otlp_endpoint: Option<String>,
+    pub service_name: String,
+}
+
+impl MeilisearchConfig {
+    pub fn client(&self) -> meilisearch_sdk::client::Client {
+        meilisearch_sdk::client::Client::new(
+            &self.url,
+            self.api_key.as_ref().map(|key| key.expose().as_str()),
+        )
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("could not read config file {}: {source}", path.display())]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("could not read secret file {}: {source}", path.display())]
+    ReadSecret {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// Carries only the position and TOML's message, not the error's
+    /// `Display`, which quotes the offending line and so any secret on it.
+    #[error("invalid config file {} at line {line}, column {column}: {message}", path.display())]
+    Parse {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    #[error("{0} is not set (use --{1}, ${2} or `{3}` in the config file)")]
+    Missing(&'static str, &'static str, &'static str, &'static str),
+    #[error("invalid {0}: {1}")]
+    Invalid(&'static str, String),
+}
+
+impl Config {
+    /// Resolves the final configuration from `args` (flags and environment),
+    /// the config file they point to, and the defaults.
+    pub fn load(args: &ConfigArgs) -> Result<Self, ConfigError> {
+        let file = match &args.config_file {
+            Some(path) => read_file(path)?,
+            None => FileConfig::default(),
+        };
+
+        let config = Config {
+            bind_address: args
+                .bind_address
+                .or(file.bind_address)
+                .unwrap_or_else(|| SocketAddr::from(DEFAULT_BIND_ADDRESS)),
+            shutdown_timeout: Duration::from_secs(
+                args.shutdown_timeout_secs
+                    .or(file.shutdown_timeout_secs)
+                    .unwrap_or(DEFAULT_SHUTDOWN_TIMEOUT_SECS),
+            ),
+            log_filter: args
+                .log_filter
+                .clone()
+                .or(file.log_filter)
+                .unwrap_or_else(|| DEFAULT_LOG_FILTER.to_owned()),
+            log_format: args.log_format.or(file.log_format).unwrap_or_default(),
+            admin_token: secret_value(
+                "admin token",
+                args.admin_token.clone(),
+                args.admin_token_file.as_deref(),
+            )?
+            .or(secret_value(
+                "admin token",
+                file.admin_token,
+                file.admin_token_file.as_deref(),
+            )?),
+            cursor_secret: secret_value(
+                "cursor secret",
+                args.cursor_secret.clone(),
+                args.cursor_secret_file.as_deref(),
+            )?
+            .or(secret_value(
+                "cursor secret",
+                file.cursor_secret,
+                file.cursor_secret_file.as_deref(),
+            )?),
+            database: DatabaseConfig {
+                url: with_password(
+                    secret_value(
+                        "database URL",
+                        args.database_url.clone(),
+                        args.database_url_file.as_deref(),
+                    )?
+                    .or(secret_value(
+                        "database URL",
+                        file.database.url,
+                        file.database.url_file.as_deref(),
+                    )?)
+                    .ok_or(ConfigError::Missing(
+                        "database URL",
+                        "database-url",
+                        "DATABASE_URL",
+                        "database.url",
+                    ))?,
+                    secret_value(
+                        "database password",
+                        args.database_password.clone(),
+                        args.database_password_file.as_deref(),
+                    )?
+                    .or(secret_value(
+                        "database password",
+                        file.database.password,
+                        file.database.password_file.as_deref(),
+                    )?),
+                )?,
+                max_connections: args
+                    .database_max_connections
+                    .or(file.database.max_connections)
+                    .unwrap_or(DEFAULT_MAX_CONNECTIONS),
+                min_idle: args.database_min_idle.or(file.database.min_idle),
+                connection_timeout: Duration::from_secs(
+                    args.database_connection_timeout_secs
+                        .or(file.database.connection_timeout_secs)
+                        .unwrap_or(DEFAULT_CONNECTION_TIMEOUT_SECS),
+                ),
+                run_migrations: args
+                    .run_migrations
+                    .or(file.database.run_migrations)
+                    .unwrap_or(false),
+            },
+            meilisearch: MeilisearchConfig {
+                url: args
+                    .meilisearch_url
+                    .clone()
+                    .or(file.meilisearch.url)
+                    .unwrap_or_else(|| DEFAULT_MEILISEARCH_URL.to_owned()),
+                api_key: secret_value(
+                    "Meilisearch API key",
+                    args.meilisearch_api_key.clone(),
+                    args.meilisearch_api_key_file.as_deref(),
+                )?
+                .or(secret_value(
+                    "Meilisearch API key",
+                    file.meilisearch.api_key,
+                    file.meilisearch.api_key_file.as_deref(),
+                )?),
+            },
+            search: SearchConfig {
+                backend: args
+                    .search_backend
+                    .or(file.search.backend)
+                    .unwrap_or_default(),
+            },
+            telemetry: TelemetryConfig {
+                otlp_endpoint: args
+                    .otlp_endpoint
+                    .clone()
+                    .or(file.telemetry.otlp_endpoint),
+                service_name: args
+                    .service_name
+                    .clone()
+                    .or(file.telemetry.service_name)
+                    .unwrap_or_else(|| DEFAULT_SERVICE_NAME.to_owned()),
+            },
+        };
+        config.validate()?;
+        config.register_secrets();
+        Ok(config)
+    }
+
+    pub fn search_backend(&self) -> Arc<dyn SearchBackend> {
+        match self.search.backend {
+            SearchBackendKind::Meilisearch => {
+                Arc::new(MeilisearchBackend::new(self.meilisearch.client()))
+            }
+            SearchBackendKind::Memory => Arc::new(InMemoryBackend::new()),
+        }
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        let database = &self.database;
+        let url = database.url.expose();
+        if !(url.starts_with("postgres://") || url.starts_with("postgresql://")) {
+            return Err(ConfigError::Invalid(
+                "database URL",
+                "expected a postgres:// or postgresql:// URL".to_owned(),
+            ));
+        }
+        if let Ok(parsed) = url::Url::parse(url) {
+            let password = parsed.password().map(|password| {
+                percent_encoding::percent_decode_str(password).decode_utf8_lossy()
+            });
+            if password.is_some_and(|password| password.len() < secret::MIN_SCRUB_LEN) {
+                return Err(ConfigError::Invalid(
+                    "database password",
+                    format!(
+                        "must be at least {} bytes, or it cannot be scrubbed from logs",
+                        secret::MIN_SCRUB_LEN
+                    ),
+                ));
+            }
+        }
+        if database.max_connections == 0 {
+            return Err(ConfigError::Invalid(
+                "database max connections",
+                "must be at least 1".to_owned(),
+            ));
+        }
+        if let Some(min_idle) = database.min_idle {
+            if min_idle > database.max_connections {
+                return Err(ConfigError::Invalid(
+                    "database min idle",
+                    format!(
+                        "{min_idle} exceeds max connections ({})",
+                        database.max_connections
+                    ),
+                ));
+            }
+        }
+        if database.connection_timeout.is_zero() {
+            return Err(ConfigError::Invalid(
+                "database connection timeout",
+                "must be at least 1 second".to_owned(),
+            ));
+        }
+
+        let meilisearch = &self.meilisearch;
+        if !(meilisearch.url.starts_with("http://") || meilisearch.url.starts_with("https://")) {
+            return Err(ConfigError::Invalid(
+                "Meilisearch URL",
+                format!(
+                    "expected an http:// or https:// URL, got `{}`",
+                    meilisearch.url
+                ),
+            ));
+        }
+        if meilisearch
+            .api_key
+            .as_ref()
+            .is_some_and(|key| key.expose().is_empty())
+        {
+            return Err(ConfigError::Invalid(
+                "Meilisearch API key",
+                "must not be empty; leave it unset instead".to_owned(),
+            ));
+        }
+
+        if let Some(endpoint) = &self.telemetry.otlp_endpoint {
+            if !(endpoint.starts_with("http://") || endpoint.starts_with("https://")) {
+                return Err(ConfigError::Invalid(
+                    "OTLP endpoint",
+                    format!("expected an http:// or https:// URL, got `{endpoint}`"),
+                ));
+            }
+        }
+
+        if self
+            .admin_token
+            .as_ref()
+            .is_some_and(|token| token.expose().is_empty())
+        {
+            return Err(ConfigError::Invalid(
+                "admin token",
+                "must not be empty; leave it unset to disable /admin".to_owned(),
+            ));
+        }
+
+        if self
+            .cursor_secret
+            .as_ref()
+            .is_some_and(|secret| secret.expose().len() < MIN_CURSOR_SECRET_LEN)
+        {
+            return Err(ConfigError::Invalid(
+                "cursor secret",
+                format!("must be at least {MIN_CURSOR_SECRET_LEN} bytes"),
+            ));
+        }
+
+        tracing_subscriber::EnvFilter::try_new(&self.log_filter)
+            .map_err(|err| ConfigError::Invalid("log filter", err.to_string()))?;
+        Ok(())
+    }
+
+    /// Lets [`secret::scrub`] strip credentials from error messages.
+    fn register_secrets(&self) {
+        if let Ok(url) = url::Url::parse(self.database.url.expose()) {
+            if let Some(password) = url.password() {
+                secret::register(password);
+                if let Ok(decoded) = percent_encoding::percent_decode_str(password).decode_utf8() {
+                    secret::register(&decoded);
+                }
+            }
+        }
+        if let Some(api_key) = &self.meilisearch.api_key {
+            secret::register(api_key.expose());
+        }
+        if let Some(token) = &self.admin_token {
+            secret::register(token.expose());
+        }
+        if let Some(cursor_secret) = &self.cursor_secret {
+            secret::register(cursor_secret.expose());
+        }
+    }
+}
+
+/// Resolves one layer of a secret that may be given inline or as a `*_FILE`
+/// path, refusing ambiguous setups where both are present.
+fn secret_value(
+    name: &'static str,
+    value: Option<Secret<String>>,
+    file: Option<&Path>,
+) -> Result<Option<Secret<String>>, ConfigError> {
+    match (value, file) {
+        (Some(_), Some(_)) => Err(ConfigError::Invalid(
+            name,
+            "set either the value or its file, not both".to_owned(),
+        )),
+        (Some(value), None) => Ok(Some(value)),
+        (None, Some(path)) => {
+            secret::read_file(path)
+                .map(Some)
+                .map_err(|source| ConfigError::ReadSecret {
+                    path: path.to_owned(),
+                    source,
+                })
+        }
+        (None, None) => Ok(None),
+    }
+}
+
+fn with_password(
+    url: Secret<String>,
+    password: Option<Secret<String>>,
+) -> Result<Secret<String>, ConfigError> {
+    let Some(password) = password else {
+        return Ok(url);
+    };
+    // the parse error is deliberately dropped: it could quote the URL
+    let mut parsed = url::Url::parse(url.expose())
+        .map_err(|_| ConfigError::Invalid("database URL", "not a valid URL".to_owned()))?;
+    parsed
+        .set_password(Some(password.expose()))
+        .map_err(|()| ConfigError::Invalid("database URL", "cannot carry a password".to_owned()))?;
+    Ok(Secret::new(parsed.into()))
+}
+
+fn read_file(path: &Path) -> Result<FileConfig, ConfigError> {
+    let contents = std::fs::read_to_string(path).map_err(|source| ConfigError::Read {
+        path: path.to_owned(),
+        source,
+    })?;
+    toml::from_str(&contents).map_err(|err| {
+        let before = &contents[..err.span().map_or(0, |span| span.start)];
+        ConfigError::Parse {
+            path: path.to_owned(),
+            line: before.matches('\n').count() + 1,
+            column: before.rsplit('\n').next().unwrap_or("").chars().count() + 1,
+            message: err.message().trim_end().to_owned(),
+        }
+    })
+}
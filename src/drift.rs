@@ -0,0 +1,260 @@
+//! Compares the live database with the `table!` definitions in
+//! [`crate::schema`]. Diesel trusts those definitions blindly, so a column
+//! whose type or nullability changed underneath them only shows up as
+//! deserialization errors at query time.
+
+use std::fmt;
+
+use diesel::{
+    prelude::*,
+    sql_types::{self, is_nullable, Array, SqlType},
+};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+
+use crate::schema::{outbox, reindex_holds, users};
+
+/// Postgres type names (`information_schema.columns.udt_name`) a diesel SQL
+/// type can be read from.
+pub trait PgTypeName {
+    const UDT_NAMES: &'static [&'static str];
+}
+
+macro_rules! pg_type_names {
+    ($($sql_type:ty => [$($udt:literal),+],)+) => {
+        $(impl PgTypeName for $sql_type {
+            const UDT_NAMES: &'static [&'static str] = &[$($udt),+];
+        })+
+    };
+}
+
+pg_type_names! {
+    sql_types::Bool => ["bool"],
+    sql_types::SmallInt => ["int2"],
+    sql_types::Integer => ["int4"],
+    sql_types::BigInt => ["int8"],
+    sql_types::Float => ["float4"],
+    sql_types::Double => ["float8"],
+    sql_types::Text => ["text", "varchar", "bpchar"],
+    sql_types::Timestamp => ["timestamp"],
+    sql_types::Timestamptz => ["timestamptz"],
+    sql_types::Date => ["date"],
+    sql_types::Uuid => ["uuid"],
+}
+
+impl<T: PgTypeName + SqlType> PgTypeName for sql_types::Nullable<T> {
+    const UDT_NAMES: &'static [&'static str] = T::UDT_NAMES;
+}
+
+trait Nullability {
+    const NULLABLE: bool;
+}
+
+impl Nullability for is_nullable::IsNullable {
+    const NULLABLE: bool = true;
+}
+
+impl Nullability for is_nullable::NotNull {
+    const NULLABLE: bool = false;
+}
+
+/// A column as declared in `table!`.
+#[derive(Debug)]
+struct Declared {
+    name: &'static str,
+    sql_type: String,
+    udt_names: &'static [&'static str],
+    nullable: bool,
+}
+
+trait DeclaredColumn {
+    fn declared() -> Declared;
+}
+
+impl<C> DeclaredColumn for C
+where
+    C: Column,
+    C::SqlType: SqlType + PgTypeName,
+    <C::SqlType as SqlType>::IsNull: Nullability,
+{
+    fn declared() -> Declared {
+        Declared {
+            name: C::NAME,
+            sql_type: short_type_name(std::any::type_name::<C::SqlType>()),
+            udt_names: C::SqlType::UDT_NAMES,
+            nullable: <C::SqlType as SqlType>::IsNull::NULLABLE,
+        }
+    }
+}
+
+/// `diesel::sql_types::Nullable<diesel::sql_types::Text>` -> `Nullable<Text>`
+fn short_type_name(full: &str) -> String {
+    let mut short = String::new();
+    let mut path = String::new();
+    for c in full.chars().chain(std::iter::once(' ')) {
+        if c.is_alphanumeric() || c == '_' || c == ':' {
+            path.push(c);
+            continue;
+        }
+        short.push_str(path.rsplit("::").next().unwrap_or_default());
+        path.clear();
+        short.push(c);
+    }
+    short.pop();
+    short
+}
+
+/// Implemented for the `all_columns` tuple of every table.
+trait DeclaredColumns {
+    fn declared() -> Vec<Declared>;
+}
+
+macro_rules! declared_columns {
+    ($($column:ident),+) => {
+        impl<$($column: DeclaredColumn),+> DeclaredColumns for ($($column,)+) {
+            fn declared() -> Vec<Declared> {
+                vec![$($column::declared()),+]
+            }
+        }
+    };
+}
+
+declared_columns!(A);
+declared_columns!(A, B);
+declared_columns!(A, B, C);
+declared_columns!(A, B, C, D);
+declared_columns!(A, B, C, D, E);
+declared_columns!(A, B, C, D, E, F);
+declared_columns!(A, B, C, D, E, F, G);
+declared_columns!(A, B, C, D, E, F, G, H);
+
+fn declared<T: DeclaredColumns>(_all_columns: T) -> Vec<Declared> {
+    T::declared()
+}
+
+/// One difference between `table!` and the database.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Drift {
+    MissingTable {
+        table: &'static str,
+    },
+    MissingColumn {
+        table: &'static str,
+        column: &'static str,
+    },
+    Type {
+        table: &'static str,
+        column: &'static str,
+        declared: String,
+        actual: String,
+    },
+    Nullability {
+        table: &'static str,
+        column: &'static str,
+        declared_nullable: bool,
+    },
+}
+
+impl fmt::Display for Drift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Drift::MissingTable { table } => write!(f, "table `{table}` does not exist"),
+            Drift::MissingColumn { table, column } => {
+                write!(f, "column `{table}.{column}` does not exist")
+            }
+            Drift::Type {
+                table,
+                column,
+                declared,
+                actual,
+            } => write!(
+                f,
+                "column `{table}.{column}` is declared `{declared}` but has type `{actual}`"
+            ),
+            Drift::Nullability {
+                table,
+                column,
+                declared_nullable: true,
+            } => write!(
+                f,
+                "column `{table}.{column}` is declared `Nullable` but is NOT NULL"
+            ),
+            Drift::Nullability {
+                table,
+                column,
+                declared_nullable: false,
+            } => write!(
+                f,
+                "column `{table}.{column}` is declared NOT NULL but is nullable"
+            ),
+        }
+    }
+}
+
+#[derive(QueryableByName)]
+struct LiveColumn {
+    #[diesel(sql_type = sql_types::Text)]
+    table_name: String,
+    #[diesel(sql_type = sql_types::Text)]
+    column_name: String,
+    #[diesel(sql_type = sql_types::Text)]
+    udt_name: String,
+    #[diesel(sql_type = sql_types::Bool)]
+    nullable: bool,
+}
+
+/// Lists every difference between [`crate::schema`] and the tables in the
+/// connection's current schema. Extra database columns are not drift:
+/// diesel never reads them.
+pub async fn check(conn: &mut AsyncPgConnection) -> QueryResult<Vec<Drift>> {
+    let tables = [
+        ("users", declared(users::all_columns)),
+        ("outbox", declared(outbox::all_columns)),
+        ("reindex_holds", declared(reindex_holds::all_columns)),
+    ];
+    let names: Vec<&str> = tables.iter().map(|(name, _)| *name).collect();
+    let live: Vec<LiveColumn> = diesel::sql_query(
+        "SELECT table_name::text, column_name::text, udt_name::text, \
+                is_nullable = 'YES' AS nullable \
+         FROM information_schema.columns \
+         WHERE table_schema = current_schema() AND table_name = ANY($1)",
+    )
+    .bind::<Array<sql_types::Text>, _>(&names)
+    .load(conn)
+    .await?;
+
+    let mut drift = Vec::new();
+    for (table, columns) in tables {
+        if !live.iter().any(|live| live.table_name == table) {
+            drift.push(Drift::MissingTable { table });
+            continue;
+        }
+        for column in columns {
+            let Some(found) = live
+                .iter()
+                .find(|live| live.table_name == table && live.column_name == column.name)
+            else {
+                drift.push(Drift::MissingColumn {
+                    table,
+                    column: column.name,
+                });
+                continue;
+            };
+            if !column.udt_names.contains(&found.udt_name.as_str()) {
+                drift.push(Drift::Type {
+                    table,
+                    column: column.name,
+                    declared: column.sql_type,
+                    actual: found.udt_name.clone(),
+                });
+            }
+            if column.nullable != found.nullable {
+                drift.push(Drift::Nullability {
+                    table,
+                    column: column.name,
+                    declared_nullable: column.nullable,
+                });
+            }
+        }
+    }
+    Ok(drift)
+}
@@ -0,0 +1,160 @@
+use axum::{
+    extract::rejection::{JsonRejection, PathRejection, QueryRejection},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use diesel::result::DatabaseErrorKind;
+use diesel_async::pooled_connection::PoolError;
+
+use crate::{
+    backend::SearchError,
+    problem::{FieldError, Problem},
+    secret,
+};
+
+/// Every way a handler can fail. Each variant maps to a fixed HTTP status and a
+/// stable `code` that clients can match on; the underlying error is only logged,
+/// never sent over the wire.
+#[derive(Debug, thiserror::Error)]
+pub enum AppError {
+    #[error("{0}")]
+    NotFound(String),
+    #[error("invalid request body: {0}")]
+    InvalidBody(#[from] JsonRejection),
+    #[error("request body failed validation: {0}")]
+    Validation(#[from] validator::ValidationErrors),
+    #[error("invalid path parameter: {0}")]
+    InvalidPath(#[from] PathRejection),
+    #[error("invalid query string: {0}")]
+    InvalidQuery(#[from] QueryRejection),
+    #[error("invalid pagination cursor")]
+    InvalidCursor,
+    #[error("database error: {0}")]
+    Database(#[from] diesel::result::Error),
+    #[error("connection pool error: {0}")]
+    Pool(#[from] bb8::RunError<PoolError>),
+    #[error("search error: {0}")]
+    Search(#[from] SearchError),
+    #[error("missing or invalid admin token")]
+    Unauthorized,
+    #[error("invalid log filter: {0}")]
+    InvalidLogFilter(#[from] tracing_subscriber::filter::ParseError),
+    #[error("could not reload the log filter: {0}")]
+    LogReload(#[from] tracing_subscriber::reload::Error),
+}
+
+impl AppError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::InvalidBody(rejection) => rejection.status(),
+            AppError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::InvalidPath(rejection) => rejection.status(),
+            AppError::InvalidQuery(rejection) => rejection.status(),
+            AppError::InvalidCursor => StatusCode::BAD_REQUEST,
+            AppError::Database(diesel::result::Error::NotFound) => StatusCode::NOT_FOUND,
+            AppError::Database(diesel::result::Error::DatabaseError(kind, _)) => match kind {
+                DatabaseErrorKind::UniqueViolation => StatusCode::CONFLICT,
+                DatabaseErrorKind::CheckViolation => StatusCode::UNPROCESSABLE_ENTITY,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            },
+            AppError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            AppError::Pool(_) => StatusCode::SERVICE_UNAVAILABLE,
+            AppError::Search(_) => StatusCode::BAD_GATEWAY,
+            AppError::Unauthorized => StatusCode::UNAUTHORIZED,
+            AppError::InvalidLogFilter(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::LogReload(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Machine-readable identifier, part of the public API: do not rename.
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::NotFound(_) => "not_found",
+            AppError::InvalidBody(_) => "invalid_body",
+            AppError::Validation(_) => "validation_failed",
+            AppError::InvalidPath(_) => "invalid_path",
+            AppError::InvalidQuery(_) => "invalid_query",
+            AppError::InvalidCursor => "invalid_cursor",
+            AppError::Database(diesel::result::Error::NotFound) => "not_found",
+            AppError::Database(diesel::result::Error::DatabaseError(kind, _)) => match kind {
+                DatabaseErrorKind::UniqueViolation => "unique_violation",
+                DatabaseErrorKind::CheckViolation => "check_violation",
+                _ => "database_error",
+            },
+            AppError::Database(_) => "database_error",
+            AppError::Pool(bb8::RunError::TimedOut) => "pool_timeout",
+            AppError::Pool(bb8::RunError::User(_)) => "database_unavailable",
+            AppError::Search(_) => "search_unavailable",
+            AppError::Unauthorized => "unauthorized",
+            AppError::InvalidLogFilter(_) => "invalid_log_filter",
+            AppError::LogReload(_) => "log_reload_failed",
+        }
+    }
+
+    /// Client-facing message. Not-found, extractor and log filter errors
+    /// describe the request itself; everything else gets a canned description
+    /// so database details stay server-side.
+    pub fn message(&self) -> String {
+        match self {
+            AppError::NotFound(message) => message.clone(),
+            AppError::InvalidBody(rejection) => rejection.body_text(),
+            AppError::Validation(_) => "request body failed validation".to_owned(),
+            AppError::InvalidPath(rejection) => rejection.body_text(),
+            AppError::InvalidQuery(rejection) => rejection.body_text(),
+            AppError::InvalidCursor => "cursor is invalid for this listing".to_owned(),
+            AppError::Database(diesel::result::Error::NotFound) => "resource not found".to_owned(),
+            AppError::Database(diesel::result::Error::DatabaseError(kind, _)) => match kind {
+                DatabaseErrorKind::UniqueViolation => "resource already exists".to_owned(),
+                DatabaseErrorKind::CheckViolation => "value violates a constraint".to_owned(),
+                _ => "database error".to_owned(),
+            },
+            AppError::Database(_) => "database error".to_owned(),
+            AppError::Pool(bb8::RunError::TimedOut) => {
+                "timed out waiting for a database connection".to_owned()
+            }
+            AppError::Pool(bb8::RunError::User(_)) => "database unavailable".to_owned(),
+            AppError::Search(_) => "search service unavailable".to_owned(),
+            AppError::Unauthorized => "missing or invalid admin token".to_owned(),
+            AppError::InvalidLogFilter(err) => format!("invalid log filter: {err}"),
+            AppError::LogReload(_) => "could not reload the log filter".to_owned(),
+        }
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        let error = secret::scrub(&self.to_string());
+        if status.is_server_error() {
+            tracing::error!(%error, code = self.code(), "request failed");
+        } else {
+            tracing::debug!(%error, code = self.code(), "request rejected");
+        }
+        let mut problem = Problem::new(status, self.code(), self.message());
+        if let AppError::Validation(errors) = &self {
+            problem.errors = Some(field_errors(errors));
+        }
+        problem.into_response()
+    }
+}
+
+/// Flattens `errors` into one entry per failed rule, ordered by field.
+fn field_errors(errors: &validator::ValidationErrors) -> Vec<FieldError> {
+    let mut fields: Vec<_> = errors.field_errors().into_iter().collect();
+    fields.sort_by_key(|(field, _)| *field);
+    fields
+        .into_iter()
+        .flat_map(|(field, errors)| {
+            errors.iter().map(move |error| FieldError {
+                field: field.to_owned(),
+                code: error.code.to_string(),
+                message: error.message.as_deref().unwrap_or("is invalid").to_owned(),
+            })
+        })
+        .collect()
+}
+
+pub fn user_not_found(id: i32) -> AppError {
+    AppError::NotFound(format!("user {id} not found"))
+}
@@ -0,0 +1,76 @@
+use diesel::{
+    prelude::*,
+    sql_types::{BigInt, Nullable, Text},
+};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+
+use crate::{
+    models::User,
+    search::{highlighted_fields, SearchSort, UserHit},
+};
+
+#[derive(QueryableByName)]
+struct TextMatch {
+    #[diesel(embed)]
+    user: User,
+    #[diesel(sql_type = Text)]
+    name_highlight: String,
+    #[diesel(sql_type = BigInt)]
+    total_hits: i64,
+}
+
+/// Postgres full-text search over `users.name_tsv`, used when Meilisearch is
+/// unavailable. Mirrors the Meilisearch query: same filter, sort and page
+/// semantics, with `<em>` highlighting from `ts_headline`.
+pub async fn search_users(
+    conn: &mut AsyncPgConnection,
+    query: &str,
+    hair_color: Option<&str>,
+    sort: Option<SearchSort>,
+    page: usize,
+    hits_per_page: usize,
+) -> QueryResult<(Vec<UserHit>, usize)> {
+    let order = match sort {
+        Some(SearchSort::IdAsc) => "users.id ASC",
+        Some(SearchSort::IdDesc) => "users.id DESC",
+        Some(SearchSort::NameAsc) => "users.name ASC, users.id ASC",
+        Some(SearchSort::NameDesc) => "users.name DESC, users.id ASC",
+        None => "ts_rank(users.name_tsv, q) DESC, users.id ASC",
+    };
+    let sql = format!(
+        "SELECT users.id, users.name, users.hair_color, \
+                ts_headline('simple', users.name, q, \
+                            'StartSel=<em>, StopSel=</em>, HighlightAll=true') AS name_highlight, \
+                count(*) OVER () AS total_hits \
+         FROM users, websearch_to_tsquery('simple', $1) AS q \
+         WHERE ($1 = '' OR users.name_tsv @@ q) \
+           AND ($2::text IS NULL OR users.hair_color = $2) \
+         ORDER BY {order} \
+         LIMIT $3 OFFSET $4"
+    );
+    let offset = page.saturating_sub(1).saturating_mul(hits_per_page);
+    let rows: Vec<TextMatch> = diesel::sql_query(sql)
+        .bind::<Text, _>(query)
+        .bind::<Nullable<Text>, _>(hair_color)
+        .bind::<BigInt, _>(hits_per_page as i64)
+        .bind::<BigInt, _>(i64::try_from(offset).unwrap_or(i64::MAX))
+        .load(conn)
+        .await?;
+
+    let total_hits = rows
+        .iter()
+        .map(|row| row.total_hits as usize)
+        .next()
+        .unwrap_or(0);
+    let hits = rows
+        .into_iter()
+        .map(|row| UserHit {
+            highlight: highlighted_fields([(
+                "name".to_owned(),
+                serde_json::Value::String(row.name_highlight),
+            )]),
+            user: row.user,
+        })
+        .collect();
+    Ok((hits, total_hits))
+}
@@ -0,0 +1,144 @@
+//! Hair colors: one of a fixed set of names or a hex code, always held in the
+//! canonical form that the `users_hair_color_canonical` check constraint
+//! accepts (a name from [`NAMES`] or `#rrggbb` in lower case).
+
+use std::{fmt, str::FromStr};
+
+use diesel::{
+    deserialize::{self, FromSql, FromSqlRow},
+    expression::AsExpression,
+    pg::{Pg, PgValue},
+    serialize::{self, Output, ToSql},
+    sql_types::Text,
+};
+
+/// Canonical color names. Keep in sync with the check constraint in
+/// `migrations/2026-10-15-110000_canonical_hair_color`.
+pub const NAMES: &[&str] = &[
+    "auburn",
+    "black",
+    "blonde",
+    "brown",
+    "chestnut",
+    "dark brown",
+    "ginger",
+    "gray",
+    "light brown",
+    "platinum",
+    "red",
+    "silver",
+    "strawberry blonde",
+    "white",
+];
+
+/// Accepted spellings of a canonical name.
+const ALIASES: &[(&str, &str)] = &[
+    ("blond", "blonde"),
+    ("grey", "gray"),
+    ("strawberry blond", "strawberry blonde"),
+];
+
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    serde::Serialize,
+    serde::Deserialize,
+    AsExpression,
+    FromSqlRow,
+)]
+#[diesel(sql_type = Text)]
+#[serde(try_from = "String")]
+pub struct HairColor(String);
+
+#[derive(Debug, thiserror::Error)]
+#[error("unknown hair color {0:?}")]
+pub struct UnknownHairColor(String);
+
+impl HairColor {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Accepts names case-insensitively with any mix of spaces, hyphens and
+/// underscores between words (`Strawberry-Blond` is `strawberry blonde`), and
+/// `#rgb` or `#rrggbb` hex codes in either case.
+impl FromStr for HairColor {
+    type Err = UnknownHairColor;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let canonical = match s.strip_prefix('#') {
+            Some(hex) => canonical_hex(hex),
+            None => canonical_name(s),
+        };
+        canonical
+            .map(HairColor)
+            .ok_or_else(|| UnknownHairColor(s.to_owned()))
+    }
+}
+
+fn canonical_hex(hex: &str) -> Option<String> {
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let hex = hex.to_ascii_lowercase();
+    match hex.len() {
+        6 => Some(format!("#{hex}")),
+        3 => Some(
+            std::iter::once('#')
+                .chain(hex.chars().flat_map(|c| [c, c]))
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+fn canonical_name(name: &str) -> Option<String> {
+    let name = name
+        .split(|c: char| c.is_whitespace() || c == '-' || c == '_')
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ");
+    let name = ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == name)
+        .map_or(name.as_str(), |(_, canonical)| canonical);
+    NAMES.contains(&name).then(|| name.to_owned())
+}
+
+impl TryFrom<String> for HairColor {
+    type Error = UnknownHairColor;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<HairColor> for String {
+    fn from(color: HairColor) -> Self {
+        color.0
+    }
+}
+
+impl fmt::Display for HairColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl ToSql<Text, Pg> for HairColor {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        <str as ToSql<Text, Pg>>::to_sql(&self.0, out)
+    }
+}
+
+impl FromSql<Text, Pg> for HairColor {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        Ok(<String as FromSql<Text, Pg>>::from_sql(bytes)?.parse()?)
+    }
+}
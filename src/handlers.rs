@@ -0,0 +1,277 @@
+use std::sync::Arc;
+
+use axum::{
+    debug_handler,
+    extract::{OriginalUri, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+};
+use diesel::{
+    dsl::sql,
+    prelude::*,
+    sql_types::{Bool, Integer, Text},
+};
+use diesel_async::{scoped_futures::ScopedFutureExt, AsyncConnection, RunQueryDsl};
+
+use crate::{
+    backend::SearchBackend,
+    error::{user_not_found, AppError},
+    extract::{Json, Path, Query, ValidatedJson},
+    indexer,
+    metrics::Metrics,
+    models::{NewUser, UpdateUser, User},
+    pagination::{
+        Cursor, CursorKey, ListParams, UserOrder, UserPage, DEFAULT_LIMIT, MAX_LIMIT,
+    },
+    schema::users,
+    search::{IndexChange, IndexSync, SyncOptions},
+    AppState, Database, DatabaseConnection,
+};
+
+#[debug_handler(state = AppState)]
+pub async fn create_user(
+    database: Database,
+    State(metrics): State<Arc<Metrics>>,
+    State(search): State<Arc<dyn SearchBackend>>,
+    Query(sync): Query<SyncOptions>,
+    ValidatedJson(new_user): ValidatedJson<NewUser>,
+) -> Result<(IndexSync, Json<User>), AppError> {
+    let mut conn = database.connect().await?;
+    let (res, entry) = metrics
+        .query(
+            "users.insert",
+            conn.transaction::<_, AppError, _>(|conn| {
+                async move {
+                    let res = diesel::insert_into(users::table)
+                        .values(new_user)
+                        .returning(User::as_returning())
+                        .get_result(conn)
+                        .await?;
+                    let entry = indexer::enqueue(conn, res.id).await?;
+                    Ok((res, entry))
+                }
+                .scope_boxed()
+            }),
+        )
+        .await?;
+    // the pool is not held up while the search backend answers
+    drop(conn);
+    let indexed = indexer::deliver_now(
+        &database,
+        &*search,
+        entry,
+        IndexChange::Upsert(&res),
+        &sync,
+    )
+    .await;
+    Ok((indexed, Json(res)))
+}
+
+pub async fn list_users(
+    database: Database,
+    State(metrics): State<Arc<Metrics>>,
+    State(cursor_key): State<Arc<CursorKey>>,
+    OriginalUri(uri): OriginalUri,
+    Query(params): Query<ListParams>,
+) -> Result<(HeaderMap, Json<UserPage>), AppError> {
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let after = params
+        .cursor
+        .as_deref()
+        .map(|token| cursor_key.verify(token))
+        .transpose()?;
+    let order = match (&after, params.order) {
+        (Some(cursor), Some(order)) if cursor.order() != order => {
+            return Err(AppError::InvalidCursor)
+        }
+        (Some(cursor), _) => cursor.order(),
+        (None, order) => order.unwrap_or_default(),
+    };
+
+    // one row past the page tells whether there is a next one
+    let mut query = users::table
+        .select(User::as_select())
+        .limit(limit + 1)
+        .into_boxed();
+    query = match order {
+        UserOrder::Id => query.order(users::id),
+        UserOrder::Name => query.order((users::name, users::id)),
+    };
+    query = match after {
+        None => query,
+        Some(Cursor::Id { id }) => query.filter(users::id.gt(id)),
+        // a row comparison, so Postgres can seek in `users_name_id_idx`
+        Some(Cursor::Name { name, id }) => query.filter(
+            sql::<Bool>("(users.name, users.id) > (")
+                .bind::<Text, _>(name)
+                .sql(", ")
+                .bind::<Integer, _>(id)
+                .sql(")"),
+        ),
+    };
+    let mut conn = database.connect().await?;
+    let mut res = metrics.query("users.list", query.load(&mut conn)).await?;
+
+    let mut headers = HeaderMap::new();
+    let mut next_cursor = None;
+    if res.len() as i64 > limit {
+        res.truncate(limit as usize);
+        let last = res.last().expect("limit is at least 1");
+        let token = cursor_key.sign(&Cursor::after(last, order));
+        let link = format!(
+            "<{}?limit={limit}&order={}&cursor={token}>; rel=\"next\"",
+            uri.path(),
+            order.as_str()
+        );
+        headers.insert(
+            header::LINK,
+            HeaderValue::try_from(link).expect("paths and cursors are visible ASCII"),
+        );
+        next_cursor = Some(token);
+    }
+    Ok((
+        headers,
+        Json(UserPage {
+            users: res,
+            next_cursor,
+        }),
+    ))
+}
+
+pub async fn get_user(
+    DatabaseConnection(mut conn): DatabaseConnection,
+    State(metrics): State<Arc<Metrics>>,
+    Path(id): Path<i32>,
+) -> Result<Json<User>, AppError> {
+    let res = metrics
+        .query(
+            "users.get",
+            users::table
+                .find(id)
+                .select(User::as_select())
+                .first(&mut conn),
+        )
+        .await
+        .optional()?
+        .ok_or_else(|| user_not_found(id))?;
+    Ok(Json(res))
+}
+
+pub async fn replace_user(
+    database: Database,
+    State(metrics): State<Arc<Metrics>>,
+    State(search): State<Arc<dyn SearchBackend>>,
+    Path(id): Path<i32>,
+    Query(sync): Query<SyncOptions>,
+    ValidatedJson(new_user): ValidatedJson<NewUser>,
+) -> Result<(IndexSync, Json<User>), AppError> {
+    let mut conn = database.connect().await?;
+    let (res, entry) = metrics
+        .query(
+            "users.replace",
+            conn.transaction::<_, AppError, _>(|conn| {
+                async move {
+                    let res = diesel::update(users::table.find(id))
+                        .set(new_user)
+                        .returning(User::as_returning())
+                        .get_result(conn)
+                        .await
+                        .optional()?
+                        .ok_or_else(|| user_not_found(id))?;
+                    let entry = indexer::enqueue(conn, res.id).await?;
+                    Ok((res, entry))
+                }
+                .scope_boxed()
+            }),
+        )
+        .await?;
+    drop(conn);
+    let indexed = indexer::deliver_now(
+        &database,
+        &*search,
+        entry,
+        IndexChange::Upsert(&res),
+        &sync,
+    )
+    .await;
+    Ok((indexed, Json(res)))
+}
+
+pub async fn update_user(
+    database: Database,
+    State(metrics): State<Arc<Metrics>>,
+    State(search): State<Arc<dyn SearchBackend>>,
+    Path(id): Path<i32>,
+    Query(sync): Query<SyncOptions>,
+    ValidatedJson(changes): ValidatedJson<UpdateUser>,
+) -> Result<(IndexSync, Json<User>), AppError> {
+    let mut conn = database.connect().await?;
+    let (res, entry) = metrics
+        .query(
+            "users.update",
+            conn.transaction::<_, AppError, _>(|conn| {
+                async move {
+                    // diesel refuses to build an UPDATE without any SET clause
+                    let query = users::table.find(id);
+                    let res = if changes.is_empty() {
+                        query.select(User::as_select()).first(conn).await
+                    } else {
+                        diesel::update(query)
+                            .set(changes)
+                            .returning(User::as_returning())
+                            .get_result(conn)
+                            .await
+                    };
+                    let res = res.optional()?.ok_or_else(|| user_not_found(id))?;
+                    let entry = indexer::enqueue(conn, res.id).await?;
+                    Ok((res, entry))
+                }
+                .scope_boxed()
+            }),
+        )
+        .await?;
+    drop(conn);
+    let indexed = indexer::deliver_now(
+        &database,
+        &*search,
+        entry,
+        IndexChange::Upsert(&res),
+        &sync,
+    )
+    .await;
+    Ok((indexed, Json(res)))
+}
+
+pub async fn delete_user(
+    database: Database,
+    State(metrics): State<Arc<Metrics>>,
+    State(search): State<Arc<dyn SearchBackend>>,
+    Path(id): Path<i32>,
+    Query(sync): Query<SyncOptions>,
+) -> Result<(IndexSync, StatusCode), AppError> {
+    let mut conn = database.connect().await?;
+    let entry = metrics
+        .query(
+            "users.delete",
+            conn.transaction::<_, AppError, _>(|conn| {
+                async move {
+                    let deleted = diesel::delete(users::table.find(id)).execute(conn).await?;
+                    if deleted == 0 {
+                        return Err(user_not_found(id));
+                    }
+                    Ok(indexer::enqueue(conn, id).await?)
+                }
+                .scope_boxed()
+            }),
+        )
+        .await?;
+    drop(conn);
+    let indexed = indexer::deliver_now(
+        &database,
+        &*search,
+        entry,
+        IndexChange::Delete(id),
+        &sync,
+    )
+    .await;
+    Ok((indexed, StatusCode::NO_CONTENT))
+}
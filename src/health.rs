@@ -0,0 +1,143 @@
+use std::time::{Duration, Instant};
+
+use axum::{extract::State, http::StatusCode, Json};
+use diesel_async::{AsyncPgConnection, SimpleAsyncConnection};
+
+use crate::{error::AppError, secret, AppState};
+
+/// Upper bound for each dependency check, so a hung dependency reports as
+/// down instead of stalling the probe past the orchestrator's own timeout.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Up,
+    Down,
+    /// Every pooled connection is checked out; new requests have to queue.
+    Saturated,
+}
+
+#[derive(serde::Serialize)]
+pub struct Check {
+    status: CheckStatus,
+    latency_ms: f64,
+    /// [`AppError::code`] of the failure; the details are only logged.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<&'static str>,
+}
+
+#[derive(serde::Serialize)]
+pub struct PoolCheck {
+    status: CheckStatus,
+    connections: u32,
+    idle_connections: u32,
+    max_size: u32,
+}
+
+#[derive(serde::Serialize)]
+pub struct Checks {
+    database: Check,
+    pool: PoolCheck,
+    search: Check,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadinessStatus {
+    Ready,
+    /// Serving, but searches fall back to Postgres full-text search.
+    Degraded,
+    NotReady,
+}
+
+#[derive(serde::Serialize)]
+pub struct Readiness {
+    status: ReadinessStatus,
+    checks: Checks,
+}
+
+/// Liveness: answers as long as the process can serve HTTP at all.
+pub async fn healthz() -> Json<serde_json::Value> {
+    Json(serde_json::json!({ "status": "ok" }))
+}
+
+/// Readiness: `200` while the database is reachable and the pool has room,
+/// `503` otherwise. Search is reported but does not make the service unready,
+/// since searches fall back to Postgres.
+pub async fn readyz(State(state): State<AppState>) -> (StatusCode, Json<Readiness>) {
+    let (database, search) = tokio::join!(
+        timed("database", async {
+            let mut conn = state.pool.get().await?;
+            select_one(&mut conn).await
+        }),
+        timed("search", async {
+            Ok::<_, AppError>(state.search.health().await?)
+        }),
+    );
+
+    let pool_state = state.pool.state();
+    let pool = PoolCheck {
+        status: if pool_state.connections >= state.pool_max_size
+            && pool_state.idle_connections == 0
+        {
+            CheckStatus::Saturated
+        } else {
+            CheckStatus::Up
+        },
+        connections: pool_state.connections,
+        idle_connections: pool_state.idle_connections,
+        max_size: state.pool_max_size,
+    };
+
+    let status = if database.status != CheckStatus::Up || pool.status != CheckStatus::Up {
+        ReadinessStatus::NotReady
+    } else if search.status != CheckStatus::Up {
+        ReadinessStatus::Degraded
+    } else {
+        ReadinessStatus::Ready
+    };
+    let code = match status {
+        ReadinessStatus::NotReady => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::OK,
+    };
+    let checks = Checks {
+        database,
+        pool,
+        search,
+    };
+    (code, Json(Readiness { status, checks }))
+}
+
+async fn select_one(conn: &mut AsyncPgConnection) -> Result<(), AppError> {
+    Ok(conn.batch_execute("SELECT 1").await?)
+}
+
+async fn timed<F>(name: &'static str, check: F) -> Check
+where
+    F: std::future::Future<Output = Result<(), AppError>>,
+{
+    let started = Instant::now();
+    let outcome = tokio::time::timeout(CHECK_TIMEOUT, check).await;
+    let latency_ms = started.elapsed().as_secs_f64() * 1000.0;
+    let error = match outcome {
+        Ok(Ok(())) => None,
+        Ok(Err(err)) => {
+            tracing::warn!(check = name, error = %secret::scrub(&err.to_string()), "readiness check failed");
+            Some(err.code())
+        }
+        Err(_) => {
+            tracing::warn!(check = name, timeout = ?CHECK_TIMEOUT, "readiness check timed out");
+            Some("timeout")
+        }
+    };
+    Check {
+        status: if error.is_some() {
+            CheckStatus::Down
+        } else {
+            CheckStatus::Up
+        },
+        latency_ms,
+        error,
+    }
+}
@@ -0,0 +1,303 @@
+use std::{collections::BTreeSet, sync::Arc, time::Duration};
+
+use chrono::{DateTime, Utc};
+use diesel::{
+    dsl::{exists, not, now},
+    prelude::*,
+};
+use diesel_async::{
+    scoped_futures::ScopedFutureExt, AsyncConnection, AsyncPgConnection, RunQueryDsl,
+};
+use tokio::sync::watch;
+
+use crate::{
+    backend::{bounded, SearchBackend, SearchError},
+    error::AppError,
+    metrics::Metrics,
+    models::User,
+    schema::{outbox, reindex_holds, users},
+    search::{sync_user, IndexChange, IndexSync, SyncOptions, USERS_INDEX},
+    secret, Database, DbPool,
+};
+
+/// How long a new entry is reserved for the request that wrote it before the
+/// worker treats the inline delivery as lost and picks it up.
+const INLINE_GRACE: chrono::Duration = chrono::Duration::seconds(30);
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+const SUBMIT_TIMEOUT: Duration = Duration::from_secs(10);
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long a worker holds the entries it is delivering: longer than a
+/// batch can take, which is two submissions of up to [`SUBMIT_TIMEOUT`] and
+/// two task waits of up to [`DELIVERY_TIMEOUT`]. A worker that dies
+/// mid-delivery only delays its entries by this much.
+const LEASE: chrono::Duration = chrono::Duration::seconds(90);
+const BATCH_SIZE: i64 = 100;
+const MAX_BACKOFF_SECS: i64 = 300;
+
+#[derive(Queryable, Selectable)]
+#[diesel(table_name = outbox)]
+struct OutboxEntry {
+    id: i64,
+    user_id: i32,
+    attempts: i32,
+}
+
+/// Records that `user_id` needs reindexing. Must run in the same transaction
+/// as the `users` change so the two commit or roll back together.
+pub async fn enqueue(conn: &mut AsyncPgConnection, user_id: i32) -> QueryResult<i64> {
+    diesel::insert_into(outbox::table)
+        .values((
+            outbox::user_id.eq(user_id),
+            outbox::available_at.eq(Utc::now() + INLINE_GRACE),
+        ))
+        .returning(outbox::id)
+        .get_result(conn)
+        .await
+}
+
+/// Tries to index the change right after commit, then hands the outbox entry
+/// to the worker instead of deleting it. Being accepted by the search backend
+/// does not mean the task will succeed, and a concurrent write to the same
+/// user can reach the backend in the other order; the worker re-reads the
+/// current row after this send, so its delivery is what settles the index.
+///
+/// Callers must not hold a connection across this call: the send can take as
+/// long as the search backend's timeouts, and the release takes a fresh
+/// checkout. If that fails, the worker still picks the entry up once the
+/// inline grace period runs out.
+pub async fn deliver_now(
+    database: &Database,
+    search: &dyn SearchBackend,
+    entry_id: i64,
+    change: IndexChange<'_>,
+    options: &SyncOptions,
+) -> IndexSync {
+    let indexed = sync_user(search, change, options).await;
+    let released = async {
+        let mut conn = database.connect().await?;
+        database
+            .metrics
+            .query(
+                "outbox.release",
+                diesel::update(outbox::table.find(entry_id))
+                    .set(outbox::available_at.eq(Utc::now()))
+                    .execute(&mut conn),
+            )
+            .await?;
+        Ok::<_, AppError>(())
+    };
+    if let Err(err) = released.await {
+        tracing::warn!(error = %err, entry_id, "failed to release outbox entry to the worker");
+    }
+    indexed
+}
+
+/// Drains the outbox into the search index until `shutdown` flips to
+/// `true`. Spawned once from `main`.
+pub async fn run_worker(
+    pool: DbPool,
+    metrics: Arc<Metrics>,
+    search: Arc<dyn SearchBackend>,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    while !*shutdown.borrow() {
+        match drain_batch(&pool, &metrics, &*search, Utc::now()).await {
+            Ok(Drained { entries: 0, .. }) => {}
+            Ok(Drained { entries, .. }) => {
+                tracing::debug!(entries, "drained search outbox batch");
+                continue;
+            }
+            Err(err) => {
+                let error = secret::scrub(&err.to_string());
+                tracing::error!(%error, "search outbox worker failed");
+            }
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(POLL_INTERVAL) => {}
+            _ = shutdown.changed() => {}
+        }
+    }
+}
+
+/// Delivers everything enqueued so far, including entries still reserved
+/// for inline delivery. Used on shutdown once no requests are left in
+/// flight; stops at the first failed batch, leaving the rest for the next
+/// start. Delivers nothing while a reindex holds the outbox.
+pub async fn flush(
+    pool: &DbPool,
+    metrics: &Metrics,
+    search: &dyn SearchBackend,
+) -> Result<usize, AppError> {
+    let due = Utc::now() + INLINE_GRACE;
+    let mut flushed = 0;
+    loop {
+        match drain_batch(pool, metrics, search, due).await? {
+            Drained { entries: 0, .. } => return Ok(flushed),
+            Drained {
+                entries,
+                delivered: true,
+            } => flushed += entries,
+            Drained {
+                entries,
+                delivered: false,
+            } => {
+                tracing::warn!(entries, "search outbox entries left undelivered");
+                return Ok(flushed);
+            }
+        }
+    }
+}
+
+struct Drained {
+    entries: usize,
+    delivered: bool,
+}
+
+/// Delivers one batch of entries available at `due`, reporting how many were
+/// processed and whether the search backend accepted them.
+///
+/// Entries are claimed with `SKIP LOCKED` and a [`LEASE`] in a short
+/// transaction, so several workers can share the table and no connection or
+/// row lock is held while the search backend works. Each affected user is
+/// indexed from its current row (or removed from the index if the row is
+/// gone), which keeps redelivery idempotent.
+async fn drain_batch(
+    pool: &DbPool,
+    metrics: &Metrics,
+    search: &dyn SearchBackend,
+    due: DateTime<Utc>,
+) -> Result<Drained, AppError> {
+    let (entries, user_ids, upserts) = {
+        let mut conn = pool.get().await?;
+        let entries = metrics.query("outbox.claim", claim(&mut conn, due)).await?;
+        let user_ids: BTreeSet<i32> = entries.iter().map(|entry| entry.user_id).collect();
+        let upserts: Vec<User> = metrics
+            .query(
+                "users.load",
+                users::table
+                    .filter(users::id.eq_any(&user_ids))
+                    .select(User::as_select())
+                    .load(&mut conn),
+            )
+            .await?;
+        (entries, user_ids, upserts)
+    };
+    if entries.is_empty() {
+        return Ok(Drained {
+            entries: 0,
+            delivered: true,
+        });
+    }
+    let deletes: Vec<i32> = user_ids
+        .iter()
+        .copied()
+        .filter(|id| !upserts.iter().any(|user| user.id == *id))
+        .collect();
+
+    let pushed = push(search, &upserts, &deletes).await;
+    let mut conn = pool.get().await?;
+    match &pushed {
+        Ok(()) => {
+            let ids: Vec<i64> = entries.iter().map(|entry| entry.id).collect();
+            metrics
+                .query(
+                    "outbox.delete",
+                    diesel::delete(outbox::table.filter(outbox::id.eq_any(&ids)))
+                        .execute(&mut conn),
+                )
+                .await?;
+        }
+        Err(err) => {
+            tracing::warn!(error = %err, entries = entries.len(), "search outbox delivery failed");
+            let last_error = err.to_string();
+            let retry = async {
+                for entry in &entries {
+                    let attempts = entry.attempts + 1;
+                    diesel::update(outbox::table.find(entry.id))
+                        .set((
+                            outbox::attempts.eq(attempts),
+                            outbox::last_error.eq(&last_error),
+                            outbox::available_at.eq(Utc::now() + backoff(attempts)),
+                        ))
+                        .execute(&mut conn)
+                        .await?;
+                }
+                Ok::<_, diesel::result::Error>(())
+            };
+            metrics.query("outbox.backoff", retry).await?;
+        }
+    }
+    Ok(Drained {
+        entries: entries.len(),
+        delivered: pushed.is_ok(),
+    })
+}
+
+/// Takes up to [`BATCH_SIZE`] entries available at `due` and pushes their
+/// `available_at` past the lease, so other workers skip them until this one
+/// has delivered them or given up.
+///
+/// Takes nothing while a reindex holds the outbox (see
+/// [`crate::reindex::run`]). The hold is checked in the same statement that
+/// picks the entries, so an entry written after a hold was taken is never
+/// claimed under a snapshot that misses the hold.
+async fn claim(
+    conn: &mut AsyncPgConnection,
+    due: DateTime<Utc>,
+) -> Result<Vec<OutboxEntry>, AppError> {
+    conn.transaction::<_, AppError, _>(|conn| {
+        async move {
+            let entries: Vec<OutboxEntry> = outbox::table
+                .filter(outbox::available_at.le(due))
+                .filter(not(exists(
+                    reindex_holds::table.filter(reindex_holds::expires_at.gt(now)),
+                )))
+                .order(outbox::id)
+                .limit(BATCH_SIZE)
+                .for_update()
+                .skip_locked()
+                .select(OutboxEntry::as_select())
+                .load(conn)
+                .await?;
+            let ids: Vec<i64> = entries.iter().map(|entry| entry.id).collect();
+            diesel::update(outbox::table.filter(outbox::id.eq_any(&ids)))
+                .set(outbox::available_at.eq(Utc::now() + LEASE))
+                .execute(conn)
+                .await?;
+            Ok(entries)
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+async fn push(
+    search: &dyn SearchBackend,
+    upserts: &[User],
+    deletes: &[i32],
+) -> Result<(), SearchError> {
+    let mut tasks = Vec::new();
+    if !upserts.is_empty() {
+        tasks.push(bounded(SUBMIT_TIMEOUT, search.upsert(USERS_INDEX, upserts)).await?);
+    }
+    if !deletes.is_empty() {
+        tasks.push(bounded(SUBMIT_TIMEOUT, search.delete(USERS_INDEX, deletes)).await?);
+    }
+    for task in tasks {
+        bounded(
+            DELIVERY_TIMEOUT,
+            search.wait_for_success(task, DELIVERY_TIMEOUT),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Exponential backoff: 2s, 4s, 8s, ... capped at five minutes.
+fn backoff(attempts: i32) -> chrono::Duration {
+    let secs = 1_i64
+        .checked_shl(attempts.clamp(0, 30) as u32)
+        .unwrap_or(MAX_BACKOFF_SECS)
+        .min(MAX_BACKOFF_SECS);
+    chrono::Duration::seconds(secs)
+}
@@ -0,0 +1,228 @@
+pub mod access_log;
+pub mod admin;
+pub mod backend;
+pub mod breaker;
+pub mod config;
+pub mod drift;
+pub mod error;
+pub mod extract;
+mod fulltext;
+pub mod hair_color;
+pub mod handlers;
+pub mod health;
+pub mod indexer;
+pub mod logging;
+pub mod metrics;
+pub mod migrate;
+pub mod models;
+pub mod pagination;
+pub mod problem;
+pub mod reindex;
+pub mod schema;
+pub mod search;
+pub mod secret;
+pub mod telemetry;
+
+use axum::{async_trait, extract::{FromRef, FromRequestParts}, http::request::Parts, middleware, routing::{get, post}, Router};
+use diesel_async::{
+    pooled_connection::{AsyncDieselConnectionManager, PoolError}, AsyncPgConnection,
+};
+use std::{sync::Arc, time::Duration};
+use admin::Admin;
+use backend::SearchBackend;
+use breaker::CircuitBreaker;
+use config::DatabaseConfig;
+use error::AppError;
+use metrics::Metrics;
+use pagination::CursorKey;
+
+pub type DB = diesel::pg::Pg;
+pub type DbPoolConn =
+bb8::PooledConnection<'static, AsyncDieselConnectionManager<AsyncPgConnection>>;
+pub type DbPool = bb8::Pool<AsyncDieselConnectionManager<AsyncPgConnection>>;
+
+/// Builds the connection pool described by `config`.
+pub async fn connect(config: &DatabaseConfig) -> Result<DbPool, PoolError> {
+    let manager = AsyncDieselConnectionManager::<AsyncPgConnection>::new(config.url.expose());
+    bb8::Pool::builder()
+        .max_size(config.max_connections)
+        .min_idle(config.min_idle)
+        .connection_timeout(config.connection_timeout)
+        .build(manager)
+        .await
+}
+
+
+pub struct DatabaseConnection(pub DbPoolConn);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for DatabaseConnection
+    where
+        S: Send + Sync,
+        DbPool: FromRef<S>,
+        Arc<Metrics>: FromRef<S>,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Ok(database) = Database::from_request_parts(parts, state).await;
+
+        Ok(Self(database.connect().await?))
+    }
+}
+
+/// The pool, for handlers that take a connection only once their request
+/// body has been read and validated. Axum runs [`DatabaseConnection`] before
+/// any body extractor, so a rejected body would still cost a checkout.
+pub struct Database {
+    pool: DbPool,
+    metrics: Arc<Metrics>,
+}
+
+impl Database {
+    pub async fn connect(&self) -> Result<DbPoolConn, AppError> {
+        Ok(self.metrics.acquire(self.pool.get_owned()).await?)
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for Database
+    where
+        S: Send + Sync,
+        DbPool: FromRef<S>,
+        Arc<Metrics>: FromRef<S>,
+{
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(_parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Database {
+            pool: DbPool::from_ref(state),
+            metrics: Arc::<Metrics>::from_ref(state),
+        })
+    }
+}
+
+
+/// Shared state behind every route. Cheap to clone: all fields are handles.
+#[derive(Clone)]
+pub struct AppState{
+    pool: DbPool,
+    pool_max_size: u32,
+    search: Arc<dyn SearchBackend>,
+    search_breaker: Arc<CircuitBreaker>,
+    metrics: Arc<Metrics>,
+    admin: Option<Arc<Admin>>,
+    cursor_key: Arc<CursorKey>,
+}
+
+impl AppState {
+    /// `pool_max_size` is the `max_size` `pool` was built with, which bb8
+    /// does not expose; readiness uses it to detect a saturated pool.
+    ///
+    /// `metrics` should be the same instance the search backend was wrapped
+    /// with in [`backend::InstrumentedBackend`], so `/metrics` shows its calls.
+    pub fn new(
+        pool: DbPool,
+        pool_max_size: u32,
+        search: Arc<dyn SearchBackend>,
+        metrics: Arc<Metrics>,
+    ) -> Self {
+        AppState {
+            pool,
+            pool_max_size,
+            search,
+            // after 5 failed searches in a row, go straight to Postgres for 30s
+            search_breaker: Arc::new(CircuitBreaker::new(5, Duration::from_secs(30))),
+            metrics,
+            admin: None,
+            cursor_key: Arc::new(CursorKey::random()),
+        }
+    }
+
+    /// Enables the `/admin` routes.
+    pub fn with_admin(mut self, admin: Admin) -> Self {
+        self.admin = Some(Arc::new(admin));
+        self
+    }
+
+    /// Replaces the per-process random key that signs pagination cursors, so
+    /// cursors stay valid across restarts and replicas.
+    pub fn with_cursor_key(mut self, key: CursorKey) -> Self {
+        self.cursor_key = Arc::new(key);
+        self
+    }
+}
+
+
+impl FromRef<AppState> for DbPool {
+    fn from_ref(app_state: &AppState) -> DbPool {
+        app_state.pool.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<dyn SearchBackend> {
+    fn from_ref(state: &AppState) -> Self {
+        state.search.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<Metrics> {
+    fn from_ref(state: &AppState) -> Self {
+        state.metrics.clone()
+    }
+}
+
+impl FromRef<AppState> for Option<Arc<Admin>> {
+    fn from_ref(state: &AppState) -> Self {
+        state.admin.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<CursorKey> {
+    fn from_ref(state: &AppState) -> Self {
+        state.cursor_key.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<CircuitBreaker> {
+    fn from_ref(state: &AppState) -> Self {
+        state.search_breaker.clone()
+    }
+}
+
+/// Builds the user service router with `state` applied, ready to be served
+/// or nested under another application's router.
+///
+/// The outbox worker is not started here; spawn [`indexer::run_worker`]
+/// alongside the server.
+pub fn build_app(state: AppState) -> Router {
+    let mut router = Router::new();
+    if state.admin.is_some() {
+        router = router.route(
+            "/admin/log-filter",
+            get(admin::get_log_filter).put(admin::put_log_filter),
+        );
+    }
+    router
+        .route("/user/create", post(handlers::create_user))
+        .route("/users", get(handlers::list_users).post(handlers::create_user))
+        .route("/users/search", get(search::search_users))
+        .route(
+            "/users/:id",
+            get(handlers::get_user)
+                .put(handlers::replace_user)
+                .patch(handlers::update_user)
+                .delete(handlers::delete_user),
+        )
+        .route("/search/tasks/:uid", get(search::get_index_task))
+        .route("/healthz", get(health::healthz))
+        .route("/readyz", get(health::readyz))
+        .route("/metrics", get(metrics::metrics))
+        // a route layer runs after routing, so the route pattern is known
+        .route_layer(middleware::from_fn(access_log::record_route))
+        // a plain layer also sees 404s and 405s, which route layers miss
+        .layer(middleware::from_fn_with_state(state.clone(), metrics::track_http))
+        .layer(middleware::from_fn(problem::problem_details))
+        .layer(middleware::from_fn(access_log::access_log))
+        .with_state(state)
+}
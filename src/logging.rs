@@ -0,0 +1,66 @@
+//! Log output, and the log filter that `/admin/log-filter` swaps at runtime.
+
+use std::sync::Mutex;
+
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_sdk::trace::TracerProvider;
+use tracing_subscriber::{
+    filter::Targets, fmt, layer::SubscriberExt, reload, util::SubscriberInitExt, EnvFilter, Layer,
+};
+
+use crate::{
+    config::{Config, LogFormat},
+    error::AppError,
+};
+
+/// Handle to the filter in front of the log output.
+pub struct LogFilter {
+    reload: Box<dyn Fn(EnvFilter) -> Result<(), reload::Error> + Send + Sync>,
+    directives: Mutex<String>,
+}
+
+impl LogFilter {
+    /// Wraps `handle`, whose filter was built from `directives`.
+    pub fn new<S: 'static>(handle: reload::Handle<EnvFilter, S>, directives: &str) -> Self {
+        LogFilter {
+            reload: Box::new(move |filter| handle.reload(filter)),
+            directives: Mutex::new(directives.to_owned()),
+        }
+    }
+
+    /// The directives currently in effect.
+    pub fn directives(&self) -> String {
+        self.directives.lock().unwrap().clone()
+    }
+
+    /// Replaces the filter. Invalid directives leave the current one in place.
+    pub fn set(&self, directives: &str) -> Result<(), AppError> {
+        let filter = EnvFilter::try_new(directives)?;
+        let mut current = self.directives.lock().unwrap();
+        (self.reload)(filter)?;
+        *current = directives.to_owned();
+        Ok(())
+    }
+}
+
+/// Installs the global subscriber: log lines in the configured format behind
+/// a reloadable filter, plus trace export when `tracer_provider` is given.
+pub fn init(config: &Config, tracer_provider: Option<&TracerProvider>) -> LogFilter {
+    let (filter, handle) = reload::Layer::new(EnvFilter::new(&config.log_filter));
+    let output = match config.log_format {
+        LogFormat::Pretty => fmt::layer().boxed(),
+        LogFormat::Json => fmt::layer().json().flatten_event(true).boxed(),
+    };
+    // traces only carry this crate's spans: the log filter is for humans, and
+    // the exporter's own HTTP client must not feed spans back into itself
+    let traces = tracer_provider.map(|provider| {
+        tracing_opentelemetry::layer()
+            .with_tracer(provider.tracer(env!("CARGO_PKG_NAME")))
+            .with_filter(Targets::new().with_target(env!("CARGO_CRATE_NAME"), tracing::Level::INFO))
+    });
+    tracing_subscriber::registry()
+        .with(output.with_filter(filter))
+        .with(traces)
+        .init();
+    LogFilter::new(handle, &config.log_filter)
+}
@@ -1,134 +1,344 @@
-use axum::{async_trait, extract::{FromRef, FromRequestParts, State}, http::{request::Parts, StatusCode}, response::Json, routing::{get, post}, Router, debug_handler};
-use diesel::prelude::*;
-use diesel_async::{
-    pooled_connection::AsyncDieselConnectionManager, AsyncPgConnection, RunQueryDsl,
+use clap::Parser;
+use std::sync::Arc;
+use tokio::sync::watch;
+
+use issue::{
+    admin::Admin,
+    backend::{InstrumentedBackend, SearchBackend, TrackedBackend},
+    build_app,
+    config::{Config, ConfigArgs, SearchBackendKind},
+    drift,
+    error::AppError,
+    indexer,
+    logging::{self, LogFilter},
+    metrics::Metrics,
+    migrate,
+    pagination::CursorKey,
+    reindex, search, secret, telemetry, AppState, DbPool,
 };
-use std::net::SocketAddr;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-// normally part of your generated schema.rs file
-table! {
-    users (id) {
-        id -> Integer,
-        name -> Text,
-        hair_color -> Nullable<Text>,
-    }
-}
+const REINDEX_BATCH_SIZE: i64 = 1000;
 
-#[derive(serde::Serialize, Selectable, Queryable)]
-struct User {
-    id: i32,
-    name: String,
-    hair_color: Option<String>,
+#[derive(Parser)]
+#[command(about = "User service backed by Postgres and Meilisearch")]
+struct Cli {
+    #[command(flatten)]
+    config: ConfigArgs,
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
-#[derive(serde::Deserialize, Insertable)]
-#[diesel(table_name = users)]
-struct NewUser {
-    name: String,
-    hair_color: Option<String>,
+#[derive(clap::Subcommand)]
+enum Command {
+    /// Run the HTTP server (the default)
+    Serve,
+    /// Rebuild the Meilisearch users index from the users table
+    Reindex {
+        /// Rows read from Postgres and sent to Meilisearch per batch
+        #[arg(long, default_value_t = REINDEX_BATCH_SIZE)]
+        batch_size: i64,
+    },
+    /// Compare the database with the table definitions compiled into the binary
+    CheckSchema,
+    /// Manage the database schema
+    Migrate {
+        #[command(subcommand)]
+        action: MigrateAction,
+    },
 }
 
-pub type DB = diesel::pg::Pg;
-pub type DbPoolConn =
-bb8::PooledConnection<'static, AsyncDieselConnectionManager<AsyncPgConnection>>;
-pub type DbPool = bb8::Pool<AsyncDieselConnectionManager<AsyncPgConnection>>;
+#[derive(clap::Subcommand)]
+enum MigrateAction {
+    /// Apply all pending migrations
+    Up,
+    /// Revert the most recent migration
+    Down,
+    /// List migrations and whether they have been applied
+    Status,
+    /// Revert and reapply the most recent migration
+    Redo,
+}
 
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+    let config = match Config::load(&cli.config) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("error: {err}");
+            std::process::exit(2);
+        }
+    };
 
-pub fn internal_error<E>(err: E) -> (StatusCode, String)
-    where
-        E: std::error::Error,
-{
-    (StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
-}
+    let tracer_provider = match telemetry::tracer_provider(&config.telemetry) {
+        Ok(provider) => provider,
+        Err(err) => {
+            eprintln!("error: could not set up trace export: {err}");
+            std::process::exit(2);
+        }
+    };
+    let log_filter = logging::init(&config, tracer_provider.as_ref());
 
+    // set up connection pool
+    let pool = match issue::connect(&config.database).await {
+        Ok(pool) => pool,
+        Err(err) => {
+            tracing::error!(error = %secret::scrub(&err.to_string()), "could not set up the database pool");
+            std::process::exit(1);
+        }
+    };
 
-pub struct DatabaseConnection(pub DbPoolConn);
+    let command = cli.command.unwrap_or(Command::Serve);
+    if let Command::Migrate { action } = command {
+        if let Err(err) = migrate(&pool, action).await {
+            tracing::error!(error = %secret::scrub(&err.to_string()), "migration failed");
+            std::process::exit(1);
+        }
+        return;
+    }
 
-#[async_trait]
-impl<S> FromRequestParts<S> for DatabaseConnection
-    where
-        S: Send + Sync,
-        DbPool: FromRef<S>,
-{
-    type Rejection = (StatusCode, String);
+    let search = config.search_backend();
 
-    async fn from_request_parts(_parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
-        let pool = DbPool::from_ref(state);
+    match command {
+        Command::Serve => serve(&config, pool, search, log_filter).await,
+        Command::Reindex { batch_size } => {
+            if let Err(err) = reindex::run(&pool, &*search, batch_size).await {
+                tracing::error!(error = %secret::scrub(&err.to_string()), "users reindex failed");
+                std::process::exit(1);
+            }
+        }
+        Command::CheckSchema => match report_drift(&pool).await {
+            Ok(false) => tracing::info!("database schema matches the table definitions"),
+            Ok(true) => std::process::exit(1),
+            Err(err) => {
+                tracing::error!(error = %secret::scrub(&err.to_string()), "could not check for schema drift");
+                std::process::exit(1);
+            }
+        },
+        Command::Migrate { .. } => unreachable!("handled above"),
+    }
 
-        Ok(Self((pool.get_owned().await.map_err(internal_error)?)))
+    if let Some(provider) = tracer_provider {
+        for result in provider.force_flush() {
+            if let Err(err) = result {
+                tracing::warn!(error = %err, "could not export traces");
+            }
+        }
     }
 }
 
-
-struct AppState{
-    pool: DbPool,
-    meilisearch_client: meilisearch_sdk::client::Client,
+async fn migrate(pool: &DbPool, action: MigrateAction) -> Result<(), migrate::MigrateError> {
+    match action {
+        MigrateAction::Up => {
+            let applied = migrate::run_pending(pool).await?;
+            if applied.is_empty() {
+                tracing::info!("database schema is up to date");
+            }
+            for version in applied {
+                tracing::info!(%version, "applied migration");
+            }
+        }
+        MigrateAction::Down => {
+            let version = migrate::revert_last(pool).await?;
+            tracing::info!(%version, "reverted migration");
+        }
+        MigrateAction::Redo => {
+            let version = migrate::redo(pool).await?;
+            tracing::info!(%version, "redid migration");
+        }
+        MigrateAction::Status => {
+            for migration in migrate::status(pool).await? {
+                let mark = if migration.applied { "X" } else { " " };
+                println!("[{mark}] {}", migration.name);
+            }
+        }
+    }
+    Ok(())
 }
 
-
-impl FromRef<AppState> for DbPool {
-    fn from_ref(app_state: &AppState) -> DbPool {
-        app_state.pool.clone()
+/// Applies pending migrations if configured to, then fails if any are left:
+/// serving against an outdated schema fails in confusing ways.
+async fn check_schema(config: &Config, pool: &DbPool) -> Result<(), migrate::MigrateError> {
+    if config.database.run_migrations {
+        for version in migrate::run_pending(pool).await? {
+            tracing::info!(%version, "applied migration");
+        }
+    }
+    let pending = migrate::pending(pool).await?;
+    if !pending.is_empty() {
+        return Err(migrate::MigrateError::Pending(pending));
     }
+    Ok(())
 }
 
-impl FromRef<AppState> for meilisearch_sdk::client::Client {
-    fn from_ref(state: &AppState) -> Self {
-        state.meilisearch_client.clone()
+/// Logs every difference between `schema.rs` and the database, returning
+/// whether there were any.
+async fn report_drift(pool: &DbPool) -> Result<bool, AppError> {
+    let mut conn = pool.get().await?;
+    let drift = drift::check(&mut conn).await?;
+    for drift in &drift {
+        tracing::error!(%drift, "database schema drift");
     }
+    Ok(!drift.is_empty())
 }
 
+async fn serve(
+    config: &Config,
+    pool: DbPool,
+    search: Arc<dyn SearchBackend>,
+    log_filter: LogFilter,
+) {
+    if let Err(err) = check_schema(config, &pool).await {
+        tracing::error!(error = %secret::scrub(&err.to_string()), "database schema is not ready");
+        std::process::exit(1);
+    }
+    match report_drift(&pool).await {
+        Ok(false) => {}
+        Ok(true) => {
+            tracing::error!("database schema does not match the table definitions, refusing to serve");
+            std::process::exit(1);
+        }
+        Err(err) => {
+            tracing::error!(error = %secret::scrub(&err.to_string()), "could not check for schema drift");
+            std::process::exit(1);
+        }
+    }
 
-#[tokio::main]
-async fn main() {
-    tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "example_diesel_async_postgres=debug".into()),
-        )
-        .with(tracing_subscriber::fmt::layer())
-        .init();
-
-    let db_url = std::env::var("DATABASE_URL").unwrap();
-
-    // set up connection pool
-    let config = AsyncDieselConnectionManager::<diesel_async::AsyncPgConnection>::new(db_url);
-    let pool = bb8::Pool::builder().build(config).await.unwrap();
+    let metrics = Arc::new(Metrics::new());
+    let search: Arc<dyn SearchBackend> = Arc::new(InstrumentedBackend::new(search, metrics.clone()));
+    if let Err(err) = search.configure(search::USERS_INDEX).await {
+        tracing::warn!(error = %err, "could not configure the users search index");
+    }
+    if config.search.backend == SearchBackendKind::Memory {
+        // an in-memory index starts out empty on every run
+        if let Err(err) = reindex::run(&pool, &*search, REINDEX_BATCH_SIZE).await {
+            tracing::warn!(error = %secret::scrub(&err.to_string()), "could not fill the in-memory search index");
+        }
+    }
 
-    let meilisearch_client = meilisearch_sdk::Client::new("http://localhost:7700", Some("a"));
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let tracked = Arc::new(TrackedBackend::new(search));
+    let search: Arc<dyn SearchBackend> = tracked.clone();
+    let mut worker = tokio::spawn(indexer::run_worker(
+        pool.clone(),
+        metrics.clone(),
+        search.clone(),
+        shutdown_rx.clone(),
+    ));
 
     // build our application with some routes
-    let app = Router::new()
-        .route("/user/create", post(create_user))
-        .with_state(AppState{pool, meilisearch_client});
+    let mut state = AppState::new(
+        pool.clone(),
+        config.database.max_connections,
+        search.clone(),
+        metrics.clone(),
+    );
+    if let Some(token) = &config.admin_token {
+        state = state.with_admin(Admin::new(token.clone(), log_filter));
+    }
+    match &config.cursor_secret {
+        Some(secret) => {
+            state = state.with_cursor_key(CursorKey::new(secret.expose().as_bytes()));
+        }
+        None => tracing::warn!(
+            "no cursor secret configured; pagination cursors only work against this process until it restarts"
+        ),
+    }
+    let app = build_app(state);
 
     // run it with hyper
-    let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
+    let addr = config.bind_address;
+    let server = match axum::Server::try_bind(&addr) {
+        Ok(server) => server,
+        Err(err) => {
+            tracing::error!(error = %err, %addr, "could not bind the HTTP listener");
+            std::process::exit(1);
+        }
+    };
     tracing::debug!("listening on {}", addr);
-    axum::Server::bind(&addr)
-        .serve(app.into_make_service())
-        .await
-        .unwrap();
+    let mut server_rx = shutdown_rx;
+    let mut server = tokio::spawn(
+        server
+            .serve(app.into_make_service())
+            .with_graceful_shutdown(async move {
+                let _ = server_rx.changed().await;
+            }),
+    );
+
+    tokio::select! {
+        served = &mut server => {
+            match served {
+                Ok(Ok(())) => {}
+                Ok(Err(err)) => tracing::error!(error = %err, "server error"),
+                Err(err) => tracing::error!(error = %err, "server task failed"),
+            }
+            std::process::exit(1);
+        }
+        () = shutdown_signal() => {}
+    }
+
+    // everything below shares one deadline, so a stuck dependency cannot
+    // hold up the deploy past the configured timeout
+    let deadline = tokio::time::Instant::now() + config.shutdown_timeout;
+    tracing::info!(timeout = ?config.shutdown_timeout, "shutting down, draining in-flight requests");
+    let _ = shutdown_tx.send(true);
+    match tokio::time::timeout_at(deadline, &mut server).await {
+        Ok(_) => tracing::info!("all connections closed"),
+        Err(_) => {
+            tracing::warn!("shutdown timeout elapsed, dropping open connections");
+            server.abort();
+        }
+    }
+    if tokio::time::timeout_at(deadline, &mut worker).await.is_err() {
+        tracing::warn!("search outbox worker did not stop in time");
+        worker.abort();
+    }
+
+    match tokio::time::timeout_at(deadline, indexer::flush(&pool, &metrics, &*search)).await {
+        Ok(Ok(entries)) => tracing::info!(entries, "flushed search outbox"),
+        Ok(Err(err)) => {
+            tracing::warn!(error = %secret::scrub(&err.to_string()), "could not flush search outbox")
+        }
+        Err(_) => tracing::warn!("shutdown timeout elapsed while flushing search outbox"),
+    }
+    let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+    if let Err(err) = tracked.wait_for_pending(remaining).await {
+        tracing::warn!(error = %err, "search tasks still pending at shutdown");
+    }
+
+    let state = pool.state();
+    drop(pool);
+    tracing::info!(
+        connections = state.connections,
+        "closed database pool, shutdown complete"
+    );
 }
 
-#[debug_handler(state = AppState)]
-async fn create_user(
-    // State(appstate): State<AppState>,
-    // State(pool): State<DbPool>,
-    State(DatabaseConnection(mut conn)): State<DbPool>,
-    State(meilisearch_client): State<meilisearch_sdk::client::Client>,
-    Json(new_user): Json<NewUser>,
-) -> Result<Json<User>, (StatusCode, String)> {
-    // let mut conn = appstate.pool.get().await.unwrap();
-    // let mut conn = pool.get_owned().await.unwrap();
-
-
-    let res = diesel::insert_into(users::table)
-        .values(new_user)
-        .returning(User::as_returning())
-        .get_result(&mut conn)
-        .await
-        .unwrap();
-    Ok(Json(res))
+/// Resolves on Ctrl-C, or SIGTERM on Unix (what orchestrators send).
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            tracing::error!(error = %err, "could not listen for Ctrl-C");
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "could not listen for SIGTERM");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        () = ctrl_c => {}
+        () = terminate => {}
+    }
 }
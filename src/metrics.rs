@@ -0,0 +1,243 @@
+//! Prometheus metrics, served in the text exposition format on `/metrics`.
+//!
+//! Everything is registered on a [`Metrics`] owned by [`AppState`] rather
+//! than the process-wide default registry, so every app instance (and every
+//! test) counts on its own.
+
+use std::{future::Future, sync::Arc, time::Instant};
+
+use axum::{
+    extract::{MatchedPath, State},
+    http::{header, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry,
+    TextEncoder,
+};
+use tracing::Instrument;
+
+use crate::{AppState, DbPool};
+
+pub struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_duration: HistogramVec,
+    pool_connections: IntGauge,
+    pool_idle_connections: IntGauge,
+    pool_max_size: IntGauge,
+    pool_waiters: IntGauge,
+    pool_acquire: Histogram,
+    query_duration: HistogramVec,
+    search_calls: IntCounterVec,
+    search_duration: HistogramVec,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry = Registry::new();
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests handled"),
+            &["method", "route", "status"],
+        )
+        .unwrap();
+        let http_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "Time from receiving a request to producing its response",
+            ),
+            &["method", "route"],
+        )
+        .unwrap();
+        let pool_connections =
+            IntGauge::new("db_pool_connections", "Database connections currently open").unwrap();
+        let pool_idle_connections = IntGauge::new(
+            "db_pool_idle_connections",
+            "Open database connections not checked out",
+        )
+        .unwrap();
+        let pool_max_size =
+            IntGauge::new("db_pool_max_size", "Most connections the pool will open").unwrap();
+        let pool_waiters = IntGauge::new(
+            "db_pool_waiters",
+            "Requests currently waiting for a database connection",
+        )
+        .unwrap();
+        let pool_acquire = Histogram::with_opts(HistogramOpts::new(
+            "db_pool_acquire_seconds",
+            "Time spent waiting for a database connection",
+        ))
+        .unwrap();
+        let query_duration = HistogramVec::new(
+            HistogramOpts::new("db_query_duration_seconds", "Database query durations"),
+            &["query", "outcome"],
+        )
+        .unwrap();
+        let search_calls = IntCounterVec::new(
+            Opts::new("search_backend_calls_total", "Calls to the search backend"),
+            &["operation", "outcome"],
+        )
+        .unwrap();
+        let search_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "search_backend_call_duration_seconds",
+                "Search backend call durations",
+            ),
+            &["operation"],
+        )
+        .unwrap();
+
+        for collector in [
+            Box::new(http_requests.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(http_duration.clone()),
+            Box::new(pool_connections.clone()),
+            Box::new(pool_idle_connections.clone()),
+            Box::new(pool_max_size.clone()),
+            Box::new(pool_waiters.clone()),
+            Box::new(pool_acquire.clone()),
+            Box::new(query_duration.clone()),
+            Box::new(search_calls.clone()),
+            Box::new(search_duration.clone()),
+        ] {
+            registry
+                .register(collector)
+                .expect("metric names are unique");
+        }
+
+        Metrics {
+            registry,
+            http_requests,
+            http_duration,
+            pool_connections,
+            pool_idle_connections,
+            pool_max_size,
+            pool_waiters,
+            pool_acquire,
+            query_duration,
+            search_calls,
+            search_duration,
+        }
+    }
+
+    /// Awaits `get`, a pool checkout, counting the caller as a waiter until
+    /// it has a connection.
+    pub async fn acquire<T, E>(&self, get: impl Future<Output = Result<T, E>>) -> Result<T, E> {
+        self.pool_waiters.inc();
+        // uncounts the caller even if it is cancelled while waiting
+        let waiting = DecOnDrop(&self.pool_waiters);
+        let started = Instant::now();
+        let conn = get
+            .instrument(tracing::info_span!("db.pool.checkout"))
+            .await;
+        self.pool_acquire.observe(started.elapsed().as_secs_f64());
+        drop(waiting);
+        conn
+    }
+
+    /// Runs `query` in a span named `name`, recording its duration.
+    pub async fn query<T, E>(
+        &self,
+        name: &'static str,
+        query: impl Future<Output = Result<T, E>>,
+    ) -> Result<T, E> {
+        let span = tracing::info_span!(
+            "db.query",
+            otel.name = name,
+            otel.kind = "client",
+            otel.status_code = tracing::field::Empty,
+            db.system = "postgresql",
+        );
+        let started = Instant::now();
+        let result = query.instrument(span.clone()).await;
+        let outcome = if result.is_ok() {
+            "ok"
+        } else {
+            span.record("otel.status_code", "ERROR");
+            "error"
+        };
+        self.query_duration
+            .with_label_values(&[name, outcome])
+            .observe(started.elapsed().as_secs_f64());
+        result
+    }
+
+    /// Records one search backend call.
+    pub fn observe_search(&self, operation: &str, ok: bool, started: Instant) {
+        let outcome = if ok { "ok" } else { "error" };
+        self.search_calls
+            .with_label_values(&[operation, outcome])
+            .inc();
+        self.search_duration
+            .with_label_values(&[operation])
+            .observe(started.elapsed().as_secs_f64());
+    }
+
+    /// Encodes every metric, sampling the pool state first.
+    pub fn render(&self, pool: &DbPool, pool_max_size: u32) -> String {
+        let state = pool.state();
+        self.pool_connections.set(state.connections.into());
+        self.pool_idle_connections
+            .set(state.idle_connections.into());
+        self.pool_max_size.set(pool_max_size.into());
+
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .expect("writing to a Vec cannot fail");
+        String::from_utf8(buffer).expect("the text format is UTF-8")
+    }
+}
+
+struct DecOnDrop<'a>(&'a IntGauge);
+
+impl Drop for DecOnDrop<'_> {
+    fn drop(&mut self) {
+        self.0.dec();
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Metrics::new()
+    }
+}
+
+/// Counts and times every request, labelled with the route pattern (e.g.
+/// `/users/:id`) so ids do not blow up the number of series.
+/// Requests for unknown paths are labelled `unmatched`.
+pub async fn track_http<B>(
+    State(metrics): State<Arc<Metrics>>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or("unmatched", |path| path.as_str())
+        .to_owned();
+    let started = Instant::now();
+
+    let response = next.run(request).await;
+
+    metrics
+        .http_duration
+        .with_label_values(&[&method, &route])
+        .observe(started.elapsed().as_secs_f64());
+    metrics
+        .http_requests
+        .with_label_values(&[&method, &route, response.status().as_str()])
+        .inc();
+    response
+}
+
+pub async fn metrics(State(state): State<AppState>) -> Response {
+    let body = state.metrics.render(&state.pool, state.pool_max_size);
+    (
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, prometheus::TEXT_FORMAT)],
+        body,
+    )
+        .into_response()
+}
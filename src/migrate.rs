@@ -0,0 +1,225 @@
+//! Schema migrations from `migrations/`, compiled into the binary.
+//!
+//! `diesel_migrations` only drives synchronous connections, so each call
+//! borrows a connection from the pool and drives it from a blocking thread
+//! through [`MigrationConnection`].
+
+use diesel::{
+    connection::{
+        Connection, ConnectionSealed, LoadConnection, SimpleConnection, TransactionManager,
+        TransactionManagerStatus,
+    },
+    expression::QueryMetadata,
+    migration::MigrationVersion,
+    pg::Pg,
+    query_builder::{Query, QueryFragment, QueryId},
+    ConnectionError, ConnectionResult, QueryResult,
+};
+use diesel_async::{
+    pooled_connection::PoolError, AnsiTransactionManager, AsyncConnection, SimpleAsyncConnection,
+    TransactionManager as AsyncTransactionManager,
+};
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use futures_util::StreamExt;
+use tokio::runtime::Handle;
+
+use crate::{DbPool, DbPoolConn};
+
+pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
+
+#[derive(Debug, thiserror::Error)]
+pub enum MigrateError {
+    #[error("could not get a database connection: {0}")]
+    Pool(#[from] bb8::RunError<PoolError>),
+    #[error("migration failed: {0}")]
+    Migration(Box<dyn std::error::Error + Send + Sync>),
+    #[error("migration task did not finish: {0}")]
+    Task(#[from] tokio::task::JoinError),
+    #[error(
+        "database schema is behind, pending migrations: {}; run `issue migrate up` or start with --run-migrations",
+        .0.join(", ")
+    )]
+    Pending(Vec<String>),
+}
+
+pub struct MigrationStatus {
+    pub name: String,
+    pub applied: bool,
+}
+
+async fn with_connection<T, F>(pool: &DbPool, f: F) -> Result<T, MigrateError>
+where
+    T: Send + 'static,
+    F: FnOnce(&mut MigrationConnection) -> Result<T, MigrateError> + Send + 'static,
+{
+    let mut conn = MigrationConnection {
+        inner: pool.get_owned().await?,
+        runtime: Handle::current(),
+    };
+    tokio::task::spawn_blocking(move || f(&mut conn)).await?
+}
+
+fn versions(versions: Vec<MigrationVersion<'_>>) -> Vec<String> {
+    versions.iter().map(ToString::to_string).collect()
+}
+
+/// Applies every pending migration, returning the versions that ran.
+pub async fn run_pending(pool: &DbPool) -> Result<Vec<String>, MigrateError> {
+    with_connection(pool, |conn| {
+        conn.run_pending_migrations(MIGRATIONS)
+            .map(versions)
+            .map_err(MigrateError::Migration)
+    })
+    .await
+}
+
+/// Reverts the most recently applied migration, returning its version.
+pub async fn revert_last(pool: &DbPool) -> Result<String, MigrateError> {
+    with_connection(pool, |conn| {
+        conn.revert_last_migration(MIGRATIONS)
+            .map(|version| version.to_string())
+            .map_err(MigrateError::Migration)
+    })
+    .await
+}
+
+/// Reverts and reapplies the most recent migration, in one transaction.
+pub async fn redo(pool: &DbPool) -> Result<String, MigrateError> {
+    with_connection(pool, |conn| {
+        conn.transaction(|conn| {
+            let version = conn.revert_last_migration(MIGRATIONS)?.to_string();
+            conn.run_next_migration(MIGRATIONS)?;
+            Ok(version)
+        })
+        .map_err(MigrateError::Migration)
+    })
+    .await
+}
+
+/// Versions that are embedded but not yet applied to the database.
+pub async fn pending(pool: &DbPool) -> Result<Vec<String>, MigrateError> {
+    with_connection(pool, |conn| {
+        conn.pending_migrations(MIGRATIONS)
+            .map(|migrations| {
+                migrations
+                    .iter()
+                    .map(|migration| migration.name().version().to_string())
+                    .collect()
+            })
+            .map_err(MigrateError::Migration)
+    })
+    .await
+}
+
+/// Every embedded migration, oldest first, and whether it has been applied.
+pub async fn status(pool: &DbPool) -> Result<Vec<MigrationStatus>, MigrateError> {
+    with_connection(pool, |conn| {
+        let applied = conn.applied_migrations().map_err(MigrateError::Migration)?;
+        let mut migrations =
+            diesel::migration::MigrationSource::<diesel::pg::Pg>::migrations(&MIGRATIONS)
+                .map_err(MigrateError::Migration)?;
+        migrations.sort_by(|a, b| a.name().version().cmp(&b.name().version()));
+        Ok(migrations
+            .iter()
+            .map(|migration| MigrationStatus {
+                name: migration.name().to_string(),
+                applied: applied.contains(&migration.name().version()),
+            })
+            .collect())
+    })
+    .await
+}
+
+/// A pooled connection behind diesel's synchronous [`Connection`], blocking
+/// on the runtime for every query. Only usable from a blocking thread.
+struct MigrationConnection {
+    inner: DbPoolConn,
+    runtime: Handle,
+}
+
+type AsyncTransactions = <DbPoolConn as AsyncConnection>::TransactionManager;
+
+impl SimpleConnection for MigrationConnection {
+    fn batch_execute(&mut self, query: &str) -> QueryResult<()> {
+        self.runtime.block_on(self.inner.batch_execute(query))
+    }
+}
+
+impl ConnectionSealed for MigrationConnection {}
+
+impl Connection for MigrationConnection {
+    type Backend = Pg;
+    type TransactionManager = MigrationTransactionManager;
+
+    fn establish(_database_url: &str) -> ConnectionResult<Self> {
+        Err(ConnectionError::BadConnection(
+            "migration connections come from the pool".to_owned(),
+        ))
+    }
+
+    fn execute_returning_count<T>(&mut self, source: &T) -> QueryResult<usize>
+    where
+        T: QueryFragment<Pg> + QueryId,
+    {
+        self.runtime
+            .block_on(self.inner.execute_returning_count(source))
+    }
+
+    fn transaction_state(&mut self) -> &mut AnsiTransactionManager {
+        self.inner.transaction_state()
+    }
+}
+
+impl LoadConnection for MigrationConnection {
+    type Cursor<'conn, 'query> = std::vec::IntoIter<QueryResult<Self::Row<'conn, 'query>>>;
+    type Row<'conn, 'query> = <DbPoolConn as AsyncConnection>::Row<'conn, 'query>;
+
+    fn load<'conn, 'query, T>(
+        &'conn mut self,
+        source: T,
+    ) -> QueryResult<Self::Cursor<'conn, 'query>>
+    where
+        T: Query + QueryFragment<Pg> + QueryId + 'query,
+        Pg: QueryMetadata<T::SqlType>,
+    {
+        let rows = self.runtime.block_on(async {
+            let rows = self.inner.load(source).await?;
+            QueryResult::Ok(rows.collect::<Vec<_>>().await)
+        })?;
+        Ok(rows.into_iter())
+    }
+}
+
+impl diesel::migration::MigrationConnection for MigrationConnection {
+    fn setup(&mut self) -> QueryResult<usize> {
+        self.batch_execute(diesel::migration::CREATE_MIGRATIONS_TABLE)
+            .map(|()| 0)
+    }
+}
+
+struct MigrationTransactionManager;
+
+impl TransactionManager<MigrationConnection> for MigrationTransactionManager {
+    type TransactionStateData = AnsiTransactionManager;
+
+    fn begin_transaction(conn: &mut MigrationConnection) -> QueryResult<()> {
+        conn.runtime
+            .block_on(AsyncTransactions::begin_transaction(&mut conn.inner))
+    }
+
+    fn rollback_transaction(conn: &mut MigrationConnection) -> QueryResult<()> {
+        conn.runtime
+            .block_on(AsyncTransactions::rollback_transaction(&mut conn.inner))
+    }
+
+    fn commit_transaction(conn: &mut MigrationConnection) -> QueryResult<()> {
+        conn.runtime
+            .block_on(AsyncTransactions::commit_transaction(&mut conn.inner))
+    }
+
+    fn transaction_manager_status_mut(
+        conn: &mut MigrationConnection,
+    ) -> &mut TransactionManagerStatus {
+        AsyncTransactions::transaction_manager_status_mut(&mut conn.inner)
+    }
+}
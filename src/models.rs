@@ -0,0 +1,117 @@
+use diesel::prelude::*;
+use unicode_normalization::UnicodeNormalization;
+use validator::{Validate, ValidationError};
+
+use crate::{hair_color::HairColor, schema::users};
+
+#[derive(Clone, serde::Serialize, Selectable, Queryable, QueryableByName)]
+#[diesel(table_name = users)]
+pub struct User {
+    pub id: i32,
+    pub name: String,
+    pub hair_color: Option<HairColor>,
+}
+
+/// Hair colors stay text here so that an unknown one is reported with the
+/// other invalid fields; [`Normalize`] puts a valid one in canonical form.
+#[derive(serde::Deserialize, Insertable, AsChangeset, Validate)]
+#[diesel(table_name = users, treat_none_as_null = true)]
+pub struct NewUser {
+    #[validate(
+        length(min = 1, max = 100, message = "must be between 1 and 100 characters"),
+        custom(function = "name_characters")
+    )]
+    pub name: String,
+    #[validate(custom(function = "known_hair_color"))]
+    pub hair_color: Option<String>,
+}
+
+/// Partial update for `PATCH /users/:id`. A missing field is left untouched,
+/// while an explicit `"hair_color": null` clears the column.
+#[derive(serde::Deserialize, AsChangeset, Validate)]
+#[diesel(table_name = users)]
+pub struct UpdateUser {
+    #[validate(
+        length(min = 1, max = 100, message = "must be between 1 and 100 characters"),
+        custom(function = "name_characters")
+    )]
+    pub name: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    #[validate(custom(function = "known_hair_color"))]
+    pub hair_color: Option<Option<String>>,
+}
+
+impl UpdateUser {
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none() && self.hair_color.is_none()
+    }
+}
+
+/// Puts request input into canonical form before it is validated, so limits
+/// apply to what is stored and equal-looking names compare equal.
+pub trait Normalize {
+    fn normalize(&mut self);
+}
+
+impl Normalize for NewUser {
+    fn normalize(&mut self) {
+        normalize_text(&mut self.name);
+        self.hair_color.iter_mut().for_each(normalize_hair_color);
+    }
+}
+
+impl Normalize for UpdateUser {
+    fn normalize(&mut self) {
+        self.name.iter_mut().for_each(normalize_text);
+        self.hair_color
+            .iter_mut()
+            .flatten()
+            .for_each(normalize_hair_color);
+    }
+}
+
+/// Trims surrounding whitespace and composes characters (Unicode NFC), so a
+/// precomposed `é` and `e` + combining accent are stored the same way.
+fn normalize_text(text: &mut String) {
+    *text = text.trim().nfc().collect();
+}
+
+fn name_characters(name: &str) -> Result<(), ValidationError> {
+    if name
+        .chars()
+        .all(|c| c.is_alphanumeric() || matches!(c, ' ' | '\'' | '-' | '.'))
+    {
+        Ok(())
+    } else {
+        Err(ValidationError::new("characters").with_message(
+            "may only contain letters, digits, spaces, apostrophes, hyphens and periods".into(),
+        ))
+    }
+}
+
+/// Also rewrites a recognized hair color in its canonical form; an
+/// unrecognized one is left for validation to report.
+fn normalize_hair_color(hair_color: &mut String) {
+    normalize_text(hair_color);
+    if let Ok(canonical) = hair_color.parse::<HairColor>() {
+        *hair_color = canonical.into();
+    }
+}
+
+fn known_hair_color(hair_color: &str) -> Result<(), ValidationError> {
+    match hair_color.parse::<HairColor>() {
+        Ok(_) => Ok(()),
+        Err(_) => Err(ValidationError::new("hair_color").with_message(
+            "must be a color name such as \"brown\" or a hex code such as \"#a52a2a\"".into(),
+        )),
+    }
+}
+
+// lets serde tell an absent field apart from an explicit `null`
+fn deserialize_some<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: serde::Deserialize<'de>,
+    D: serde::Deserializer<'de>,
+{
+    serde::Deserialize::deserialize(deserializer).map(Some)
+}
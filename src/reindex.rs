@@ -0,0 +1,141 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+
+use crate::{
+    backend::SearchBackend,
+    error::AppError,
+    models::User,
+    schema::{reindex_holds, users},
+    search::USERS_INDEX,
+    DbPool,
+};
+
+const TASK_TIMEOUT: Duration = Duration::from_secs(600);
+/// How long a hold keeps the outbox worker away without being renewed:
+/// longer than the [`TASK_TIMEOUT`] wait between renewals, so only a
+/// reindex that died lets it run out.
+const HOLD_LEASE: chrono::Duration = chrono::Duration::minutes(15);
+
+/// Rebuilds the users index from Postgres.
+///
+/// Rows are read in `id` order, `batch_size` at a time, into a fresh staging
+/// index which is then swapped with the live one, so searches keep hitting
+/// the old documents until the new index is complete.
+///
+/// A row written after its batch was read would be sent to the live index
+/// only, and the swap would bring back the stale copy. So before the scan
+/// the rebuild takes a hold on the outbox, and the worker claims nothing
+/// until the swap is done: entries written meanwhile wait in the outbox and
+/// are delivered, from the current rows, to the rebuilt index. Inline
+/// deliveries still reach the old index, so searches see the change until
+/// the swap. No connection is held while the search backend works.
+pub async fn run(
+    pool: &DbPool,
+    search: &dyn SearchBackend,
+    batch_size: i64,
+) -> Result<(), AppError> {
+    let total: i64 = users::table
+        .count()
+        .get_result(&mut pool.get().await?)
+        .await?;
+    let staging_uid = format!("{USERS_INDEX}_reindex_{}", Utc::now().timestamp());
+    tracing::info!(total, staging_index = %staging_uid, "starting users reindex");
+
+    // a swap needs both sides to exist, which is not the case on a fresh install
+    search.ensure_index(USERS_INDEX, TASK_TIMEOUT).await?;
+    let task = search.create_index(&staging_uid).await?;
+    search.wait_for_success(task, TASK_TIMEOUT).await?;
+
+    let rebuilt = async {
+        let filled = fill(pool, search, &staging_uid, batch_size, total).await;
+        let indexed = match filled {
+            Ok(indexed) => indexed,
+            Err(err) => {
+                if let Err(cleanup) = search.delete_index(&staging_uid).await {
+                    tracing::warn!(error = %cleanup, "failed to remove staging index");
+                }
+                return Err(err);
+            }
+        };
+
+        hold(pool, &staging_uid).await?;
+        let task = search.swap_indexes(USERS_INDEX, &staging_uid).await?;
+        search.wait_for_success(task, TASK_TIMEOUT).await?;
+        tracing::info!(indexed, "swapped rebuilt users index into place");
+        Ok::<_, AppError>(indexed)
+    }
+    .await;
+    if let Err(err) = release(pool, &staging_uid).await {
+        tracing::warn!(error = %err, "failed to release the outbox hold, it lapses on its own");
+    }
+    let indexed = rebuilt?;
+
+    // after the swap the staging uid holds the previous documents
+    let task = search.delete_index(&staging_uid).await?;
+    search.wait_for_success(task, TASK_TIMEOUT).await?;
+    tracing::info!(indexed, total, "users reindex complete");
+    Ok(())
+}
+
+/// Copies every user into `staging_uid`, taking the outbox hold before the
+/// first batch is read and renewing it before each one after. Returns how
+/// many users were indexed.
+async fn fill(
+    pool: &DbPool,
+    search: &dyn SearchBackend,
+    staging_uid: &str,
+    batch_size: i64,
+    total: i64,
+) -> Result<usize, AppError> {
+    let task = search.configure(staging_uid).await?;
+    search.wait_for_success(task, TASK_TIMEOUT).await?;
+
+    let mut indexed = 0;
+    let mut last_id = i32::MIN;
+    loop {
+        hold(pool, staging_uid).await?;
+        let batch: Vec<User> = users::table
+            .filter(users::id.gt(last_id))
+            .order(users::id)
+            .limit(batch_size)
+            .select(User::as_select())
+            .load(&mut pool.get().await?)
+            .await?;
+        let Some(last) = batch.last() else {
+            break;
+        };
+        last_id = last.id;
+
+        let task = search.upsert(staging_uid, &batch).await?;
+        search.wait_for_success(task, TASK_TIMEOUT).await?;
+        indexed += batch.len();
+        tracing::info!(indexed, total, "reindexed users batch");
+    }
+    Ok(indexed)
+}
+
+/// Takes or renews the outbox hold for the rebuild into `staging_uid`.
+async fn hold(pool: &DbPool, staging_uid: &str) -> Result<(), AppError> {
+    let expires_at = Utc::now() + HOLD_LEASE;
+    diesel::insert_into(reindex_holds::table)
+        .values((
+            reindex_holds::staging_index.eq(staging_uid),
+            reindex_holds::expires_at.eq(expires_at),
+        ))
+        .on_conflict(reindex_holds::staging_index)
+        .do_update()
+        .set(reindex_holds::expires_at.eq(expires_at))
+        .execute(&mut pool.get().await?)
+        .await?;
+    Ok(())
+}
+
+async fn release(pool: &DbPool, staging_uid: &str) -> Result<(), AppError> {
+    diesel::delete(reindex_holds::table.find(staging_uid))
+        .execute(&mut pool.get().await?)
+        .await?;
+    Ok(())
+}
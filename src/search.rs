@@ -0,0 +1,333 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use axum::{
+    extract::State,
+    http::HeaderValue,
+    response::{IntoResponseParts, ResponseParts},
+};
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+
+use crate::{
+    backend::{bounded, SearchBackend, SearchError, SearchHit, SearchRequest},
+    breaker::CircuitBreaker,
+    error::AppError,
+    extract::{Json, Path, Query},
+    fulltext,
+    hair_color::HairColor,
+    metrics::Metrics,
+    models::User,
+    schema::users,
+    DatabaseConnection,
+};
+
+pub const USERS_INDEX: &str = "users";
+
+const WAIT_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long a write waits for the backend to accept its change. The outbox
+/// delivers it later if this runs out.
+const SUBMIT_TIMEOUT: Duration = Duration::from_secs(2);
+/// How long a search waits for the backend before answering from Postgres.
+const SEARCH_TIMEOUT: Duration = Duration::from_secs(2);
+
+const DEFAULT_HITS_PER_PAGE: usize = 20;
+const MAX_HITS_PER_PAGE: usize = 100;
+/// Deeper pages are refused by Meilisearch anyway (it stops counting at
+/// `maxTotalHits`), and an unbounded page would overflow the offset.
+const MAX_PAGE: usize = 10_000;
+
+/// Query string accepted by every write route, e.g. `?wait_for_index=true`.
+#[derive(serde::Deserialize, Default)]
+pub struct SyncOptions {
+    #[serde(default)]
+    pub wait_for_index: bool,
+}
+
+pub enum IndexChange<'a> {
+    Upsert(&'a User),
+    Delete(i32),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IndexStatus {
+    Enqueued,
+    Processing,
+    Succeeded,
+    Failed,
+    TimedOut,
+    /// The change could not even be submitted to the search backend.
+    Unavailable,
+}
+
+impl IndexStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            IndexStatus::Enqueued => "enqueued",
+            IndexStatus::Processing => "processing",
+            IndexStatus::Succeeded => "succeeded",
+            IndexStatus::Failed => "failed",
+            IndexStatus::TimedOut => "timed_out",
+            IndexStatus::Unavailable => "unavailable",
+        }
+    }
+}
+
+/// Outcome of mirroring a write into the search index, reported to the client
+/// as `x-index-task-uid` / `x-index-status` response headers.
+pub struct IndexSync {
+    pub task_uid: Option<u32>,
+    pub status: IndexStatus,
+}
+
+impl IntoResponseParts for IndexSync {
+    type Error = std::convert::Infallible;
+
+    fn into_response_parts(self, mut res: ResponseParts) -> Result<ResponseParts, Self::Error> {
+        if let Some(task_uid) = self.task_uid {
+            res.headers_mut()
+                .insert("x-index-task-uid", HeaderValue::from(task_uid));
+        }
+        res.headers_mut().insert(
+            "x-index-status",
+            HeaderValue::from_static(self.status.as_str()),
+        );
+        Ok(res)
+    }
+}
+
+/// Mirrors a committed `users` change into the search index.
+///
+/// Postgres stays the source of truth, so indexing failures are logged and
+/// reported through [`IndexSync`] rather than failing the request.
+pub async fn sync_user(
+    search: &dyn SearchBackend,
+    change: IndexChange<'_>,
+    options: &SyncOptions,
+) -> IndexSync {
+    let submitted = bounded(SUBMIT_TIMEOUT, async {
+        match change {
+            IndexChange::Upsert(user) => {
+                search.upsert(USERS_INDEX, std::slice::from_ref(user)).await
+            }
+            IndexChange::Delete(id) => search.delete(USERS_INDEX, &[id]).await,
+        }
+    })
+    .await;
+    let task_uid = match submitted {
+        Ok(task_uid) => task_uid,
+        Err(err @ SearchError::Unresponsive(_)) => {
+            tracing::warn!(error = %err, "failed to submit user to search index");
+            return IndexSync {
+                task_uid: None,
+                status: IndexStatus::TimedOut,
+            };
+        }
+        Err(err) => {
+            tracing::warn!(error = %err, "failed to submit user to search index");
+            return IndexSync {
+                task_uid: None,
+                status: IndexStatus::Unavailable,
+            };
+        }
+    };
+
+    if !options.wait_for_index {
+        return IndexSync {
+            task_uid: Some(task_uid),
+            status: IndexStatus::Enqueued,
+        };
+    }
+
+    let status = match bounded(WAIT_TIMEOUT, search.wait(task_uid, WAIT_TIMEOUT)).await {
+        Ok(task) if task.status == IndexStatus::Failed => {
+            tracing::warn!(error = task.error.as_deref().unwrap_or_default(), task_uid, "search indexing task failed");
+            IndexStatus::Failed
+        }
+        Ok(task) => task.status,
+        Err(SearchError::Timeout(_) | SearchError::Unresponsive(_)) => IndexStatus::TimedOut,
+        Err(err) => {
+            tracing::warn!(error = %err, "failed to poll search indexing task");
+            IndexStatus::Unavailable
+        }
+    };
+    IndexSync {
+        task_uid: Some(task_uid),
+        status,
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct IndexTask {
+    uid: u32,
+    status: IndexStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+pub async fn get_index_task(
+    State(search): State<Arc<dyn SearchBackend>>,
+    Path(uid): Path<u32>,
+) -> Result<Json<IndexTask>, AppError> {
+    let task = search
+        .task(uid)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("index task {uid} not found")))?;
+    Ok(Json(IndexTask {
+        uid,
+        status: task.status,
+        error: task.error,
+    }))
+}
+
+#[derive(Clone, Copy, serde::Deserialize)]
+pub enum SearchSort {
+    #[serde(rename = "id:asc")]
+    IdAsc,
+    #[serde(rename = "id:desc")]
+    IdDesc,
+    #[serde(rename = "name:asc")]
+    NameAsc,
+    #[serde(rename = "name:desc")]
+    NameDesc,
+}
+
+#[derive(serde::Deserialize)]
+pub struct SearchParams {
+    #[serde(default)]
+    q: String,
+    hair_color: Option<HairColor>,
+    page: Option<usize>,
+    hits_per_page: Option<usize>,
+    sort: Option<SearchSort>,
+}
+
+#[derive(serde::Serialize)]
+pub struct UserHit {
+    pub user: User,
+    /// Matched attributes with the query terms wrapped in `<em>` tags.
+    pub highlight: HashMap<String, String>,
+}
+
+#[derive(serde::Serialize)]
+pub struct UserSearchResults {
+    query: String,
+    hits: Vec<UserHit>,
+    page: usize,
+    hits_per_page: usize,
+    total_hits: usize,
+    total_pages: usize,
+    /// Set when the search backend was unavailable and Postgres full-text search
+    /// answered instead; ranking and typo tolerance are reduced.
+    degraded: bool,
+}
+
+pub async fn search_users(
+    DatabaseConnection(mut conn): DatabaseConnection,
+    State(metrics): State<Arc<Metrics>>,
+    State(search): State<Arc<dyn SearchBackend>>,
+    State(breaker): State<Arc<CircuitBreaker>>,
+    Query(params): Query<SearchParams>,
+) -> Result<Json<UserSearchResults>, AppError> {
+    let page = params.page.unwrap_or(1).clamp(1, MAX_PAGE);
+    let hits_per_page = params
+        .hits_per_page
+        .unwrap_or(DEFAULT_HITS_PER_PAGE)
+        .clamp(1, MAX_HITS_PER_PAGE);
+
+    if breaker.allow() {
+        let request = SearchRequest {
+            query: &params.q,
+            hair_color: params.hair_color.as_ref().map(HairColor::as_str),
+            sort: params.sort,
+            page,
+            hits_per_page,
+        };
+        match tokio::time::timeout(SEARCH_TIMEOUT, search.search(USERS_INDEX, &request)).await {
+            Ok(Ok(results)) => {
+                breaker.record_success();
+                let hits = metrics
+                    .query("users.hydrate", hydrate(&mut conn, results.hits))
+                    .await?;
+                return Ok(Json(UserSearchResults {
+                    query: params.q,
+                    hits,
+                    page,
+                    hits_per_page,
+                    total_hits: results.total_hits,
+                    total_pages: results.total_pages,
+                    degraded: false,
+                }));
+            }
+            Ok(Err(err)) => {
+                breaker.record_failure();
+                tracing::warn!(error = %err, "search backend query failed, falling back to postgres");
+            }
+            Err(_) => {
+                breaker.record_failure();
+                tracing::warn!(timeout = ?SEARCH_TIMEOUT, "search backend query timed out, falling back to postgres");
+            }
+        }
+    }
+
+    let fallback = fulltext::search_users(
+        &mut conn,
+        &params.q,
+        params.hair_color.as_ref().map(HairColor::as_str),
+        params.sort,
+        page,
+        hits_per_page,
+    );
+    let (hits, total_hits) = metrics.query("users.fulltext", fallback).await?;
+    Ok(Json(UserSearchResults {
+        query: params.q,
+        hits,
+        page,
+        hits_per_page,
+        total_hits,
+        total_pages: total_hits.div_ceil(hits_per_page),
+        degraded: true,
+    }))
+}
+
+// the index may lag behind Postgres, so hits are re-read from the users
+// table and anything deleted since indexing is dropped
+async fn hydrate(
+    conn: &mut AsyncPgConnection,
+    results: Vec<SearchHit>,
+) -> Result<Vec<UserHit>, AppError> {
+    let ids: Vec<i32> = results.iter().map(|hit| hit.id).collect();
+    let mut rows: HashMap<i32, User> = users::table
+        .filter(users::id.eq_any(&ids))
+        .select(User::as_select())
+        .load(conn)
+        .await?
+        .into_iter()
+        .map(|user| (user.id, user))
+        .collect();
+
+    let hits = results
+        .into_iter()
+        .filter_map(|hit| {
+            let user = rows.remove(&hit.id)?;
+            Some(UserHit {
+                user,
+                highlight: hit.highlight,
+            })
+        })
+        .collect();
+    Ok(hits)
+}
+
+/// Keeps only the attributes where the query actually matched.
+pub fn highlighted_fields(
+    formatted: impl IntoIterator<Item = (String, serde_json::Value)>,
+) -> HashMap<String, String> {
+    formatted
+        .into_iter()
+        .filter_map(|(field, value)| match value {
+            serde_json::Value::String(text) if text.contains("<em>") => Some((field, text)),
+            _ => None,
+        })
+        .collect()
+}
@@ -0,0 +1,91 @@
+use std::{fmt, io, path::Path, str::FromStr, sync::Mutex};
+
+const REDACTED: &str = "[REDACTED]";
+
+/// Secrets shorter than this are not scrubbed from text, since replacing
+/// every occurrence of a one or two character string would mangle output.
+/// Config validation rejects database passwords shorter than this.
+pub const MIN_SCRUB_LEN: usize = 4;
+
+static REGISTERED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// A value that must not end up in logs. `Debug` and `Display` are redacted;
+/// use [`Secret::expose`] at the point where the real value is needed.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Secret<T>(T);
+
+impl<T> Secret<T> {
+    pub fn new(value: T) -> Self {
+        Secret(value)
+    }
+
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+}
+
+impl Secret<String> {
+    /// Compares with `candidate` in time independent of where they differ, so
+    /// a token cannot be guessed byte by byte from response times.
+    pub fn matches(&self, candidate: &str) -> bool {
+        let (secret, candidate) = (self.0.as_bytes(), candidate.as_bytes());
+        secret.len() == candidate.len()
+            && secret
+                .iter()
+                .zip(candidate)
+                .fold(0, |diff, (a, b)| diff | (a ^ b))
+                == 0
+    }
+}
+
+impl<T> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Secret({REDACTED})")
+    }
+}
+
+impl<T> fmt::Display for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl FromStr for Secret<String> {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Secret(s.to_owned()))
+    }
+}
+
+impl<'de, T: serde::Deserialize<'de>> serde::Deserialize<'de> for Secret<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(Secret)
+    }
+}
+
+/// Reads a Docker/Kubernetes style secret file, dropping the trailing newline
+/// most tooling leaves behind.
+pub fn read_file(path: &Path) -> io::Result<Secret<String>> {
+    let contents = std::fs::read_to_string(path)?;
+    Ok(Secret(contents.trim_end_matches(['\r', '\n']).to_owned()))
+}
+
+/// Remembers `secret` so [`scrub`] can remove it from error messages.
+pub fn register(secret: &str) {
+    if secret.len() >= MIN_SCRUB_LEN {
+        REGISTERED.lock().unwrap().push(secret.to_owned());
+    }
+}
+
+/// Replaces every registered secret in `text`. Used on error messages from
+/// third-party crates, which may echo connection strings back.
+pub fn scrub(text: &str) -> String {
+    REGISTERED
+        .lock()
+        .unwrap()
+        .iter()
+        .fold(text.to_owned(), |text, secret| {
+            text.replace(secret.as_str(), REDACTED)
+        })
+}
@@ -0,0 +1,34 @@
+//! Optional OpenTelemetry trace export over OTLP/HTTP.
+//!
+//! Spans come from `tracing`: [`crate::access_log`] opens one per request,
+//! continuing the caller's trace from its W3C `traceparent` header, and pool
+//! checkouts, database queries and search backend calls open children of it.
+
+use opentelemetry::{trace::TraceError, KeyValue};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{
+    trace::{self, TracerProvider},
+    Resource,
+};
+
+use crate::config::TelemetryConfig;
+
+/// Builds a provider that batches spans to the configured collector, or
+/// `None` when no endpoint is configured.
+///
+/// Must be called within a Tokio runtime, which runs the batch exporter.
+pub fn tracer_provider(config: &TelemetryConfig) -> Result<Option<TracerProvider>, TraceError> {
+    let Some(endpoint) = &config.otlp_endpoint else {
+        return Ok(None);
+    };
+    let exporter = opentelemetry_otlp::new_exporter()
+        .http()
+        .with_endpoint(endpoint.trim_end_matches('/'))
+        .build_span_exporter()?;
+    let resource = Resource::new([KeyValue::new("service.name", config.service_name.clone())]);
+    let provider = TracerProvider::builder()
+        .with_batch_exporter(exporter, opentelemetry_sdk::runtime::Tokio)
+        .with_config(trace::config().with_resource(resource))
+        .build();
+    Ok(Some(provider))
+}
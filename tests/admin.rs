@@ -0,0 +1,74 @@
+mod common;
+
+use axum::{
+    body::Body,
+    http::{header, Method, Request, StatusCode},
+};
+use common::{TestApp, TestResponse, ADMIN_TOKEN, LOG_FILTER};
+use serde_json::{json, Value};
+
+async fn admin(app: &TestApp, method: Method, token: &str, body: Option<Value>) -> TestResponse {
+    let builder = Request::builder()
+        .method(method)
+        .uri("/admin/log-filter")
+        .header(header::AUTHORIZATION, format!("Bearer {token}"));
+    let request = match body {
+        Some(body) => builder
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string())),
+        None => builder.body(Body::empty()),
+    };
+    app.request(request.unwrap()).await
+}
+
+#[tokio::test]
+async fn admin_routes_require_the_token() {
+    let app = TestApp::spawn().await;
+
+    app.get("/admin/log-filter")
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED)
+        .assert_json(json!({ "code": "unauthorized" }));
+    admin(&app, Method::GET, "not-the-token", None)
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn log_filter_can_be_changed_at_runtime() {
+    let app = TestApp::spawn().await;
+
+    admin(&app, Method::GET, ADMIN_TOKEN, None)
+        .await
+        .assert_status(StatusCode::OK)
+        .assert_json(json!({ "filter": LOG_FILTER }));
+    admin(
+        &app,
+        Method::PUT,
+        ADMIN_TOKEN,
+        Some(json!({ "filter": "info,issue=debug" })),
+    )
+    .await
+    .assert_status(StatusCode::OK);
+    admin(&app, Method::GET, ADMIN_TOKEN, None)
+        .await
+        .assert_json(json!({ "filter": "info,issue=debug" }));
+}
+
+#[tokio::test]
+async fn invalid_log_filter_is_rejected() {
+    let app = TestApp::spawn().await;
+
+    admin(
+        &app,
+        Method::PUT,
+        ADMIN_TOKEN,
+        Some(json!({ "filter": "issue=loud" })),
+    )
+    .await
+    .assert_status(StatusCode::UNPROCESSABLE_ENTITY)
+    .assert_json(json!({ "code": "invalid_log_filter" }));
+    admin(&app, Method::GET, ADMIN_TOKEN, None)
+        .await
+        .assert_json(json!({ "filter": LOG_FILTER }));
+}
@@ -0,0 +1,34 @@
+use std::time::Duration;
+
+use issue::breaker::CircuitBreaker;
+
+const COOLDOWN: Duration = Duration::from_millis(50);
+
+#[test]
+fn opens_after_consecutive_failures_and_probes_after_cooldown() {
+    let breaker = CircuitBreaker::new(2, COOLDOWN);
+    breaker.record_failure();
+    assert!(breaker.allow());
+    breaker.record_failure();
+    assert!(!breaker.allow());
+
+    std::thread::sleep(COOLDOWN);
+    assert!(breaker.allow());
+    // only one probe at a time
+    assert!(!breaker.allow());
+    breaker.record_success();
+    assert!(breaker.allow());
+}
+
+#[test]
+fn a_probe_that_never_reports_back_is_given_up_on() {
+    let breaker = CircuitBreaker::new(1, COOLDOWN);
+    breaker.record_failure();
+    std::thread::sleep(COOLDOWN);
+    // the probe's request is cancelled before it records anything
+    assert!(breaker.allow());
+    assert!(!breaker.allow());
+
+    std::thread::sleep(COOLDOWN);
+    assert!(breaker.allow());
+}
@@ -0,0 +1,55 @@
+//! An OTLP/HTTP collector stand-in that keeps every span it receives.
+
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use axum::{body::Bytes, extract::State, http::StatusCode, routing::post, Router};
+use opentelemetry_proto::tonic::{
+    collector::trace::v1::ExportTraceServiceRequest, trace::v1::Span,
+};
+use prost::Message;
+
+#[derive(Clone)]
+pub struct FakeCollector {
+    spans: Arc<Mutex<Vec<Span>>>,
+    url: String,
+}
+
+impl FakeCollector {
+    pub async fn start() -> Self {
+        let spans = Arc::new(Mutex::new(Vec::new()));
+        let app = Router::new()
+            .route("/v1/traces", post(export))
+            .with_state(spans.clone());
+        let server = axum::Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0)))
+            .serve(app.into_make_service());
+        let url = format!("http://{}", server.local_addr());
+        tokio::spawn(server);
+        FakeCollector { spans, url }
+    }
+
+    /// The base URL to configure as the OTLP endpoint.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Spans received so far, in the order they were exported.
+    pub fn spans(&self) -> Vec<Span> {
+        self.spans.lock().unwrap().clone()
+    }
+}
+
+async fn export(State(spans): State<Arc<Mutex<Vec<Span>>>>, body: Bytes) -> StatusCode {
+    let Ok(request) = ExportTraceServiceRequest::decode(body) else {
+        return StatusCode::BAD_REQUEST;
+    };
+    let mut spans = spans.lock().unwrap();
+    for resource in request.resource_spans {
+        for scope in resource.scope_spans {
+            spans.extend(scope.spans);
+        }
+    }
+    StatusCode::OK
+}
@@ -0,0 +1,374 @@
+//! A Meilisearch stand-in serving the handful of endpoints the service uses,
+//! backed by in-memory maps. Every task succeeds immediately.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{Request, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{delete, get, patch, post},
+    Json, Router,
+};
+use serde_json::{json, Map, Value};
+
+#[derive(Default)]
+struct FakeState {
+    indexes: HashMap<String, BTreeMap<String, Value>>,
+    tasks: Vec<Value>,
+    unavailable: bool,
+    hanging: bool,
+}
+
+#[derive(Clone)]
+pub struct FakeMeilisearch {
+    state: Arc<Mutex<FakeState>>,
+    url: String,
+}
+
+impl FakeMeilisearch {
+    pub async fn start() -> Self {
+        let state = Arc::new(Mutex::new(FakeState::default()));
+        let app = Router::new()
+            .route("/health", get(health))
+            .route("/indexes", post(create_index))
+            .route("/indexes/:uid", delete(delete_index))
+            .route("/indexes/:uid/settings", patch(update_settings))
+            .route("/indexes/:uid/documents", post(add_documents))
+            .route("/indexes/:uid/documents/:id", delete(delete_document))
+            .route(
+                "/indexes/:uid/documents/delete-batch",
+                post(delete_documents),
+            )
+            .route("/indexes/:uid/search", post(search))
+            .route("/swap-indexes", post(swap_indexes))
+            .route("/tasks/:uid", get(get_task))
+            .layer(middleware::from_fn_with_state(state.clone(), availability))
+            .with_state(state.clone());
+
+        let server = axum::Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0)))
+            .serve(app.into_make_service());
+        let url = format!("http://{}", server.local_addr());
+        tokio::spawn(server);
+        FakeMeilisearch { state, url }
+    }
+
+    pub fn client(&self) -> meilisearch_sdk::client::Client {
+        meilisearch_sdk::client::Client::new(&self.url, Some("test-master-key"))
+    }
+
+    /// Documents currently stored in `index`, ordered by primary key.
+    pub fn documents(&self, index: &str) -> Vec<Value> {
+        let state = self.state.lock().unwrap();
+        state
+            .indexes
+            .get(index)
+            .map(|documents| documents.values().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Makes every request fail with `503`, as if the server were down.
+    pub fn set_unavailable(&self, unavailable: bool) {
+        self.state.lock().unwrap().unavailable = unavailable;
+    }
+
+    /// Makes every request wait forever, as if the server were stuck.
+    pub fn set_hanging(&self, hanging: bool) {
+        self.state.lock().unwrap().hanging = hanging;
+    }
+}
+
+type Shared = State<Arc<Mutex<FakeState>>>;
+
+async fn availability<B>(State(state): Shared, request: Request<B>, next: Next<B>) -> Response {
+    let (unavailable, hanging) = {
+        let state = state.lock().unwrap();
+        (state.unavailable, state.hanging)
+    };
+    if hanging {
+        std::future::pending::<()>().await;
+    }
+    if unavailable {
+        return (StatusCode::SERVICE_UNAVAILABLE, "unavailable").into_response();
+    }
+    next.run(request).await
+}
+
+fn now() -> String {
+    chrono::Utc::now().to_rfc3339()
+}
+
+fn meilisearch_error(status: StatusCode, code: &str, message: String) -> Response {
+    let body = json!({
+        "message": message,
+        "code": code,
+        "type": "invalid_request",
+        "link": format!("https://docs.meilisearch.com/errors#{code}"),
+    });
+    (status, Json(body)).into_response()
+}
+
+impl FakeState {
+    /// Records an already finished task and returns its `202` summary.
+    fn task(&mut self, index_uid: Option<&str>, kind: &str, error: Option<Value>) -> Response {
+        let uid = self.tasks.len() as u32;
+        let status = if error.is_some() {
+            "failed"
+        } else {
+            "succeeded"
+        };
+        let at = now();
+        self.tasks.push(json!({
+            "uid": uid,
+            "indexUid": index_uid,
+            "status": status,
+            "type": kind,
+            "details": null,
+            "error": error,
+            "canceledBy": null,
+            "duration": "PT0S",
+            "enqueuedAt": at,
+            "startedAt": at,
+            "finishedAt": at,
+        }));
+        let info = json!({
+            "taskUid": uid,
+            "indexUid": index_uid,
+            "status": "enqueued",
+            "type": kind,
+            "enqueuedAt": at,
+        });
+        (StatusCode::ACCEPTED, Json(info)).into_response()
+    }
+}
+
+async fn health() -> Json<Value> {
+    Json(json!({ "status": "available" }))
+}
+
+async fn create_index(State(state): Shared, Json(body): Json<Value>) -> Response {
+    let mut state = state.lock().unwrap();
+    let uid = body["uid"].as_str().unwrap_or_default().to_owned();
+    if state.indexes.contains_key(&uid) {
+        let error = json!({
+            "message": format!("Index `{uid}` already exists."),
+            "code": "index_already_exists",
+            "type": "invalid_request",
+            "link": "https://docs.meilisearch.com/errors#index_already_exists",
+        });
+        return state.task(Some(&uid), "indexCreation", Some(error));
+    }
+    state.indexes.insert(uid.clone(), BTreeMap::new());
+    state.task(Some(&uid), "indexCreation", None)
+}
+
+async fn delete_index(State(state): Shared, Path(uid): Path<String>) -> Response {
+    let mut state = state.lock().unwrap();
+    state.indexes.remove(&uid);
+    state.task(Some(&uid), "indexDeletion", None)
+}
+
+async fn update_settings(State(state): Shared, Path(uid): Path<String>) -> Response {
+    let mut state = state.lock().unwrap();
+    state.indexes.entry(uid.clone()).or_default();
+    state.task(Some(&uid), "settingsUpdate", None)
+}
+
+fn document_key(id: &Value) -> String {
+    // zero-padded so numeric ids sort numerically in the BTreeMap
+    match id.as_i64() {
+        Some(id) => format!("{id:020}"),
+        None => id.to_string(),
+    }
+}
+
+async fn add_documents(
+    State(state): Shared,
+    Path(uid): Path<String>,
+    Json(documents): Json<Vec<Value>>,
+) -> Response {
+    let mut state = state.lock().unwrap();
+    let index = state.indexes.entry(uid.clone()).or_default();
+    for document in documents {
+        index.insert(document_key(&document["id"]), document);
+    }
+    state.task(Some(&uid), "documentAdditionOrUpdate", None)
+}
+
+async fn delete_document(
+    State(state): Shared,
+    Path((uid, id)): Path<(String, String)>,
+) -> Response {
+    let mut state = state.lock().unwrap();
+    let id = id
+        .parse::<i64>()
+        .map(Value::from)
+        .unwrap_or(Value::String(id));
+    if let Some(index) = state.indexes.get_mut(&uid) {
+        index.remove(&document_key(&id));
+    }
+    state.task(Some(&uid), "documentDeletion", None)
+}
+
+async fn delete_documents(
+    State(state): Shared,
+    Path(uid): Path<String>,
+    Json(ids): Json<Vec<Value>>,
+) -> Response {
+    let mut state = state.lock().unwrap();
+    if let Some(index) = state.indexes.get_mut(&uid) {
+        for id in &ids {
+            index.remove(&document_key(id));
+        }
+    }
+    state.task(Some(&uid), "documentDeletion", None)
+}
+
+async fn swap_indexes(State(state): Shared, Json(swaps): Json<Vec<Value>>) -> Response {
+    let mut state = state.lock().unwrap();
+    for swap in &swaps {
+        let a = swap["indexes"][0].as_str().unwrap_or_default().to_owned();
+        let b = swap["indexes"][1].as_str().unwrap_or_default().to_owned();
+        let docs_a = state.indexes.remove(&a).unwrap_or_default();
+        let docs_b = state.indexes.remove(&b).unwrap_or_default();
+        state.indexes.insert(a, docs_b);
+        state.indexes.insert(b, docs_a);
+    }
+    state.task(None, "indexSwap", None)
+}
+
+async fn get_task(State(state): Shared, Path(uid): Path<usize>) -> Response {
+    let state = state.lock().unwrap();
+    match state.tasks.get(uid) {
+        Some(task) => Json(task.clone()).into_response(),
+        None => meilisearch_error(
+            StatusCode::NOT_FOUND,
+            "task_not_found",
+            format!("Task `{uid}` not found."),
+        ),
+    }
+}
+
+/// Parses the only filter shape the service sends: `hair_color = "..."`.
+fn parse_filter(filter: &str) -> Option<(String, String)> {
+    let (field, value) = filter.split_once(" = ")?;
+    let value = value.strip_prefix('"')?.strip_suffix('"')?;
+    Some((
+        field.to_owned(),
+        value.replace("\\\"", "\"").replace("\\\\", "\\"),
+    ))
+}
+
+fn highlight(text: &str, terms: &[String]) -> String {
+    let lower = text.to_lowercase();
+    let mut out = String::new();
+    let mut rest = 0;
+    for (start, _) in lower.char_indices() {
+        if start < rest {
+            continue;
+        }
+        if let Some(term) = terms
+            .iter()
+            .find(|term| lower[start..].starts_with(term.as_str()))
+        {
+            out.push_str(&text[rest..start]);
+            out.push_str("<em>");
+            out.push_str(&text[start..start + term.len()]);
+            out.push_str("</em>");
+            rest = start + term.len();
+        }
+    }
+    out.push_str(&text[rest..]);
+    out
+}
+
+async fn search(
+    State(state): Shared,
+    Path(uid): Path<String>,
+    Query(_): Query<HashMap<String, String>>,
+    Json(body): Json<Value>,
+) -> Response {
+    let state = state.lock().unwrap();
+    let Some(index) = state.indexes.get(&uid) else {
+        return meilisearch_error(
+            StatusCode::NOT_FOUND,
+            "index_not_found",
+            format!("Index `{uid}` not found."),
+        );
+    };
+
+    let query = body["q"].as_str().unwrap_or_default().to_owned();
+    let terms: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+    let filter = body["filter"].as_str().and_then(parse_filter);
+    let searchable = ["name", "hair_color"];
+
+    let mut hits: Vec<&Value> = index
+        .values()
+        .filter(|document| {
+            terms.iter().all(|term| {
+                searchable.iter().any(|field| {
+                    document[*field]
+                        .as_str()
+                        .is_some_and(|value| value.to_lowercase().contains(term.as_str()))
+                })
+            })
+        })
+        .filter(|document| match &filter {
+            Some((field, value)) => document[field.as_str()].as_str() == Some(value.as_str()),
+            None => true,
+        })
+        .collect();
+
+    if let Some(sort) = body["sort"][0].as_str() {
+        let (field, direction) = sort.split_once(':').unwrap_or((sort, "asc"));
+        hits.sort_by(|a, b| {
+            let ordering = match (&a[field], &b[field]) {
+                (Value::Number(a), Value::Number(b)) => {
+                    a.as_f64().partial_cmp(&b.as_f64()).unwrap()
+                }
+                (a, b) => a.as_str().cmp(&b.as_str()),
+            };
+            if direction == "desc" {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+    }
+
+    let page = body["page"].as_u64().unwrap_or(1).max(1) as usize;
+    let hits_per_page = body["hitsPerPage"].as_u64().unwrap_or(20) as usize;
+    let total_hits = hits.len();
+    let page_hits: Vec<Value> = hits
+        .into_iter()
+        .skip((page - 1) * hits_per_page)
+        .take(hits_per_page)
+        .map(|document| {
+            let mut formatted = Map::new();
+            for field in searchable {
+                if let Some(value) = document[field].as_str() {
+                    formatted.insert(field.to_owned(), Value::String(highlight(value, &terms)));
+                }
+            }
+            let mut hit = document.as_object().cloned().unwrap_or_default();
+            hit.insert("_formatted".to_owned(), Value::Object(formatted));
+            Value::Object(hit)
+        })
+        .collect();
+
+    Json(json!({
+        "hits": page_hits,
+        "query": query,
+        "page": page,
+        "hitsPerPage": hits_per_page,
+        "totalHits": total_hits,
+        "totalPages": total_hits.div_ceil(hits_per_page.max(1)),
+        "processingTimeMs": 0,
+    }))
+    .into_response()
+}
@@ -0,0 +1,291 @@
+//! In-process harness for the HTTP API. Each [`TestApp`] works inside its
+//! own rolled-back transaction and talks to its own fake Meilisearch, so
+//! tests start from empty tables and can run in parallel against one
+//! database.
+//!
+//! The database comes from `TEST_DATABASE_URL`, falling back to
+//! `DATABASE_URL`. When neither is set, [`TestApp::spawn`] panics: a
+//! database test that silently passes without a database proves nothing.
+
+// each test binary uses a different subset of the helpers
+#![allow(dead_code)]
+
+pub mod fake_collector;
+pub mod fake_meilisearch;
+
+use std::{sync::Arc, time::Duration};
+
+use axum::{
+    body::{Body, Bytes},
+    http::{header, HeaderMap, Method, Request, StatusCode},
+    Router,
+};
+use diesel_async::{
+    pooled_connection::{AsyncDieselConnectionManager, PoolError},
+    AsyncConnection, AsyncPgConnection, SimpleAsyncConnection,
+};
+use issue::{
+    admin::Admin,
+    backend::{InstrumentedBackend, MeilisearchBackend, SearchBackend},
+    build_app, indexer,
+    logging::LogFilter,
+    metrics::Metrics,
+    secret::Secret,
+    AppState, DbPool,
+};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use tower::ServiceExt;
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+pub use fake_meilisearch::FakeMeilisearch;
+
+/// Bearer token accepted by the `/admin` routes of every [`TestApp`].
+pub const ADMIN_TOKEN: &str = "test-admin-token";
+
+/// Log filter every [`TestApp`] starts with.
+pub const LOG_FILTER: &str = "info";
+
+pub struct TestApp {
+    router: Router,
+    pub pool: DbPool,
+    pub search: FakeMeilisearch,
+    metrics: Arc<Metrics>,
+    // the admin log filter reloads this; it is not installed anywhere
+    _log_filter: reload::Layer<EnvFilter, Registry>,
+}
+
+impl TestApp {
+    pub async fn spawn() -> TestApp {
+        let pool = test_pool(&database_url()).await;
+        let search = FakeMeilisearch::start().await;
+        let metrics = Arc::new(Metrics::new());
+        let backend = Arc::new(InstrumentedBackend::new(
+            Arc::new(MeilisearchBackend::new(search.client())),
+            metrics.clone(),
+        ));
+        let (log_filter, handle) = reload::Layer::new(EnvFilter::new(LOG_FILTER));
+        let admin = Admin::new(
+            Secret::new(ADMIN_TOKEN.to_owned()),
+            LogFilter::new(handle, LOG_FILTER),
+        );
+        let router =
+            build_app(AppState::new(pool.clone(), 1, backend, metrics.clone()).with_admin(admin));
+        TestApp {
+            router,
+            pool,
+            search,
+            metrics,
+            _log_filter: log_filter,
+        }
+    }
+
+    /// Delivers every pending outbox entry to `search`, as the worker would
+    /// on shutdown, and returns how many there were.
+    pub async fn flush_outbox(&self, search: &dyn SearchBackend) -> usize {
+        indexer::flush(&self.pool, &self.metrics, search)
+            .await
+            .expect("could not flush the outbox")
+    }
+
+    pub async fn request(&self, request: Request<Body>) -> TestResponse {
+        let response = self
+            .router
+            .clone()
+            .oneshot(request)
+            .await
+            .expect("router is infallible");
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = hyper::body::to_bytes(response.into_body())
+            .await
+            .expect("could not read the response body");
+        TestResponse {
+            status,
+            headers,
+            body,
+        }
+    }
+
+    pub async fn get(&self, uri: &str) -> TestResponse {
+        self.send(Method::GET, uri, None).await
+    }
+
+    pub async fn post(&self, uri: &str, body: Value) -> TestResponse {
+        self.send(Method::POST, uri, Some(body)).await
+    }
+
+    pub async fn put(&self, uri: &str, body: Value) -> TestResponse {
+        self.send(Method::PUT, uri, Some(body)).await
+    }
+
+    pub async fn patch(&self, uri: &str, body: Value) -> TestResponse {
+        self.send(Method::PATCH, uri, Some(body)).await
+    }
+
+    pub async fn delete(&self, uri: &str) -> TestResponse {
+        self.send(Method::DELETE, uri, None).await
+    }
+
+    async fn send(&self, method: Method, uri: &str, body: Option<Value>) -> TestResponse {
+        let builder = Request::builder().method(method).uri(uri);
+        let request = match body {
+            Some(body) => builder
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string())),
+            None => builder.body(Body::empty()),
+        };
+        self.request(request.unwrap()).await
+    }
+}
+
+pub struct TestResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+impl TestResponse {
+    #[track_caller]
+    pub fn assert_status(&self, expected: StatusCode) -> &Self {
+        assert_eq!(
+            self.status,
+            expected,
+            "unexpected status, body: {}",
+            String::from_utf8_lossy(&self.body)
+        );
+        self
+    }
+
+    /// Asserts that the body contains `expected`: objects may carry extra
+    /// keys, everything else must match exactly.
+    #[track_caller]
+    pub fn assert_json(&self, expected: Value) -> &Self {
+        let actual = self.json::<Value>();
+        if let Err(path) = includes(&actual, &expected, "$".to_owned()) {
+            panic!("JSON mismatch at {path}\nexpected: {expected:#}\nactual: {actual:#}");
+        }
+        self
+    }
+
+    #[track_caller]
+    pub fn json<T: DeserializeOwned>(&self) -> T {
+        serde_json::from_slice(&self.body).unwrap_or_else(|err| {
+            panic!(
+                "body is not the expected JSON ({err}): {}",
+                String::from_utf8_lossy(&self.body)
+            )
+        })
+    }
+
+    #[track_caller]
+    pub fn header(&self, name: &str) -> &str {
+        self.headers
+            .get(name)
+            .unwrap_or_else(|| panic!("missing {name} header"))
+            .to_str()
+            .expect("header is not ASCII")
+    }
+}
+
+fn includes(actual: &Value, expected: &Value, path: String) -> Result<(), String> {
+    match (actual, expected) {
+        (Value::Object(actual), Value::Object(expected)) => {
+            expected.iter().try_for_each(|(key, expected)| {
+                let path = format!("{path}.{key}");
+                match actual.get(key) {
+                    Some(actual) => includes(actual, expected, path),
+                    None => Err(path),
+                }
+            })
+        }
+        (Value::Array(actual), Value::Array(expected)) if actual.len() == expected.len() => actual
+            .iter()
+            .zip(expected)
+            .enumerate()
+            .try_for_each(|(i, (actual, expected))| {
+                includes(actual, expected, format!("{path}[{i}]"))
+            }),
+        _ if actual == expected => Ok(()),
+        _ => Err(path),
+    }
+}
+
+/// Runs every pooled connection inside a test transaction that is never
+/// committed. The schema and its migrations are created inside that same
+/// transaction, so closing the connection leaves nothing behind.
+#[derive(Debug)]
+struct TestTransaction {
+    setup: String,
+}
+
+impl TestTransaction {
+    fn new() -> Self {
+        let schema = format!("test_{}", uuid::Uuid::new_v4().simple());
+        let mut setup = format!("CREATE SCHEMA {schema}; SET LOCAL search_path TO {schema};");
+        for up in migration_scripts() {
+            setup.push_str(&std::fs::read_to_string(up).expect("could not read migration"));
+            setup.push(';');
+        }
+        TestTransaction { setup }
+    }
+}
+
+#[axum::async_trait]
+impl bb8::CustomizeConnection<AsyncPgConnection, PoolError> for TestTransaction {
+    async fn on_acquire(&self, conn: &mut AsyncPgConnection) -> Result<(), PoolError> {
+        conn.begin_test_transaction()
+            .await
+            .map_err(PoolError::QueryError)?;
+        conn.batch_execute(&self.setup)
+            .await
+            .map_err(PoolError::QueryError)
+    }
+}
+
+/// The database every test runs against.
+pub fn database_url() -> String {
+    std::env::var("TEST_DATABASE_URL")
+        .or_else(|_| std::env::var("DATABASE_URL"))
+        .expect("set TEST_DATABASE_URL to a Postgres database the tests may write to")
+}
+
+/// A pool of exactly one connection, so every request in a test sees the
+/// same uncommitted data and no other test sees any of it.
+async fn test_pool(url: &str) -> DbPool {
+    bb8::Pool::builder()
+        .max_size(1)
+        .connection_timeout(Duration::from_secs(10))
+        .connection_customizer(Box::new(TestTransaction::new()))
+        .build(AsyncDieselConnectionManager::new(url))
+        .await
+        .expect("could not build the test pool")
+}
+
+fn migration_scripts() -> Vec<std::path::PathBuf> {
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("migrations");
+    let mut scripts: Vec<_> = std::fs::read_dir(dir)
+        .expect("could not list migrations")
+        .map(|entry| entry.unwrap().path().join("up.sql"))
+        .filter(|path| path.exists())
+        .collect();
+    scripts.sort();
+    scripts
+}
+
+/// A file in the temp directory, removed on drop.
+pub struct TempFile(pub std::path::PathBuf);
+
+impl TempFile {
+    pub fn new(contents: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("issue-test-{}", uuid::Uuid::new_v4()));
+        std::fs::write(&path, contents).unwrap();
+        TempFile(path)
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
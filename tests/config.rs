@@ -0,0 +1,262 @@
+mod common;
+
+use std::{net::SocketAddr, path::PathBuf, time::Duration};
+
+use clap::Parser;
+use common::TempFile;
+use issue::{
+    config::{Config, ConfigArgs, ConfigError, LogFormat, SearchBackendKind},
+    secret::Secret,
+};
+
+const DATABASE_URL: &str = "postgres://app@db/issue";
+
+fn args() -> ConfigArgs {
+    ConfigArgs {
+        database_url: Some(Secret::new(DATABASE_URL.to_owned())),
+        ..ConfigArgs::default()
+    }
+}
+
+#[track_caller]
+fn invalid(args: &ConfigArgs) -> &'static str {
+    match Config::load(args) {
+        Err(ConfigError::Invalid(name, _)) => name,
+        Err(err) => panic!("expected an invalid setting, got: {err}"),
+        Ok(_) => panic!("expected an invalid setting, got a config"),
+    }
+}
+
+#[test]
+fn defaults_apply_when_nothing_is_set() {
+    let config = Config::load(&args()).unwrap();
+    assert_eq!(
+        config.bind_address,
+        SocketAddr::from(([127, 0, 0, 1], 3000))
+    );
+    assert_eq!(config.shutdown_timeout, Duration::from_secs(30));
+    assert_eq!(config.log_filter, "warn,issue=info");
+    assert_eq!(config.log_format, LogFormat::Pretty);
+    assert_eq!(config.database.url.expose(), DATABASE_URL);
+    assert_eq!(config.database.max_connections, 10);
+    assert_eq!(config.database.connection_timeout, Duration::from_secs(30));
+    assert!(!config.database.run_migrations);
+    assert_eq!(config.meilisearch.url, "http://localhost:7700");
+    assert_eq!(config.search.backend, SearchBackendKind::Meilisearch);
+    assert_eq!(config.telemetry.service_name, "issue");
+    assert!(config.admin_token.is_none());
+}
+
+#[test]
+fn config_file_overrides_defaults_and_args_override_the_file() {
+    let file = TempFile::new(
+        r#"
+        bind_address = "0.0.0.0:8080"
+        log_format = "json"
+
+        [database]
+        url = "postgres://file@db/issue"
+        max_connections = 20
+
+        [search]
+        backend = "memory"
+        "#,
+    );
+    let from_file = ConfigArgs {
+        config_file: Some(file.0.clone()),
+        ..ConfigArgs::default()
+    };
+    let config = Config::load(&from_file).unwrap();
+    assert_eq!(config.bind_address, SocketAddr::from(([0, 0, 0, 0], 8080)));
+    assert_eq!(config.log_format, LogFormat::Json);
+    assert_eq!(config.database.url.expose(), "postgres://file@db/issue");
+    assert_eq!(config.database.max_connections, 20);
+    assert_eq!(config.search.backend, SearchBackendKind::Memory);
+
+    let config = Config::load(&ConfigArgs {
+        database_max_connections: Some(5),
+        log_format: Some(LogFormat::Pretty),
+        ..ConfigArgs {
+            config_file: Some(file.0.clone()),
+            ..args()
+        }
+    })
+    .unwrap();
+    assert_eq!(config.database.url.expose(), DATABASE_URL);
+    assert_eq!(config.database.max_connections, 5);
+    assert_eq!(config.log_format, LogFormat::Pretty);
+    // untouched by the args
+    assert_eq!(config.bind_address, SocketAddr::from(([0, 0, 0, 0], 8080)));
+}
+
+#[test]
+fn flags_override_the_environment() {
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        config: ConfigArgs,
+    }
+
+    // the only test that parses arguments, so no other test sees this
+    std::env::set_var("SHUTDOWN_TIMEOUT_SECS", "7");
+    let from_env = Cli::try_parse_from(["issue"]).unwrap().config;
+    let from_flag = Cli::try_parse_from(["issue", "--shutdown-timeout-secs", "9"])
+        .unwrap()
+        .config;
+    std::env::remove_var("SHUTDOWN_TIMEOUT_SECS");
+
+    assert_eq!(from_env.shutdown_timeout_secs, Some(7));
+    assert_eq!(from_flag.shutdown_timeout_secs, Some(9));
+}
+
+#[test]
+fn missing_database_url_names_every_way_to_set_it() {
+    let err = Config::load(&ConfigArgs::default()).unwrap_err();
+    assert!(matches!(err, ConfigError::Missing("database URL", ..)));
+    assert_eq!(
+        err.to_string(),
+        "database URL is not set (use --database-url, $DATABASE_URL or `database.url` in the config file)"
+    );
+}
+
+#[test]
+fn invalid_settings_are_rejected() {
+    let cases = [
+        (
+            ConfigArgs {
+                database_url: Some(Secret::new("mysql://db/issue".to_owned())),
+                ..args()
+            },
+            "database URL",
+        ),
+        (
+            ConfigArgs {
+                database_url: Some(Secret::new("postgres://app:abc@db/issue".to_owned())),
+                ..args()
+            },
+            "database password",
+        ),
+        (
+            ConfigArgs {
+                database_max_connections: Some(0),
+                ..args()
+            },
+            "database max connections",
+        ),
+        (
+            ConfigArgs {
+                database_max_connections: Some(2),
+                database_min_idle: Some(3),
+                ..args()
+            },
+            "database min idle",
+        ),
+        (
+            ConfigArgs {
+                database_connection_timeout_secs: Some(0),
+                ..args()
+            },
+            "database connection timeout",
+        ),
+        (
+            ConfigArgs {
+                meilisearch_url: Some("localhost:7700".to_owned()),
+                ..args()
+            },
+            "Meilisearch URL",
+        ),
+        (
+            ConfigArgs {
+                meilisearch_api_key: Some(Secret::new(String::new())),
+                ..args()
+            },
+            "Meilisearch API key",
+        ),
+        (
+            ConfigArgs {
+                otlp_endpoint: Some("collector:4318".to_owned()),
+                ..args()
+            },
+            "OTLP endpoint",
+        ),
+        (
+            ConfigArgs {
+                admin_token: Some(Secret::new(String::new())),
+                ..args()
+            },
+            "admin token",
+        ),
+        (
+            ConfigArgs {
+                cursor_secret: Some(Secret::new("too short".to_owned())),
+                ..args()
+            },
+            "cursor secret",
+        ),
+        (
+            ConfigArgs {
+                log_filter: Some("issue=loud".to_owned()),
+                ..args()
+            },
+            "log filter",
+        ),
+    ];
+    for (args, setting) in cases {
+        assert_eq!(invalid(&args), setting);
+    }
+}
+
+#[test]
+fn secrets_are_read_from_files() {
+    let url = TempFile::new("postgres://app@db/from-file\n");
+    let password = TempFile::new("s3cret-password\n");
+    let config = Config::load(&ConfigArgs {
+        database_url_file: Some(url.0.clone()),
+        database_password_file: Some(password.0.clone()),
+        ..ConfigArgs::default()
+    })
+    .unwrap();
+    assert_eq!(
+        config.database.url.expose(),
+        "postgres://app:s3cret-password@db/from-file"
+    );
+
+    let err = Config::load(&ConfigArgs {
+        admin_token_file: Some(PathBuf::from("/nonexistent/admin_token")),
+        ..args()
+    })
+    .unwrap_err();
+    assert!(matches!(err, ConfigError::ReadSecret { .. }), "{err}");
+}
+
+#[test]
+fn a_value_and_its_file_together_are_rejected() {
+    let token = TempFile::new("file-token");
+    assert_eq!(
+        invalid(&ConfigArgs {
+            admin_token: Some(Secret::new("inline-token".to_owned())),
+            admin_token_file: Some(token.0.clone()),
+            ..args()
+        }),
+        "admin token"
+    );
+    assert_eq!(
+        invalid(&ConfigArgs {
+            database_url_file: Some(token.0.clone()),
+            ..args()
+        }),
+        "database URL"
+    );
+
+    let file = TempFile::new(&format!(
+        "[meilisearch]\napi_key = \"inline-key\"\napi_key_file = {:?}\n",
+        token.0
+    ));
+    assert_eq!(
+        invalid(&ConfigArgs {
+            config_file: Some(file.0.clone()),
+            ..args()
+        }),
+        "Meilisearch API key"
+    );
+}
@@ -0,0 +1,51 @@
+mod common;
+
+use common::TestApp;
+use diesel_async::SimpleAsyncConnection;
+use issue::drift::{self, Drift};
+
+#[tokio::test]
+async fn migrated_schema_matches_table_definitions() {
+    let app = TestApp::spawn().await;
+    let mut conn = app.pool.get().await.unwrap();
+    assert_eq!(drift::check(&mut conn).await.unwrap(), []);
+}
+
+#[tokio::test]
+async fn reports_type_nullability_and_missing_columns() {
+    let app = TestApp::spawn().await;
+    let mut conn = app.pool.get().await.unwrap();
+    conn.batch_execute(
+        "ALTER TABLE users ALTER COLUMN id TYPE BIGINT; \
+         ALTER TABLE users ALTER COLUMN hair_color SET NOT NULL; \
+         ALTER TABLE outbox DROP COLUMN last_error;",
+    )
+    .await
+    .unwrap();
+
+    let drift = drift::check(&mut conn).await.unwrap();
+    assert_eq!(
+        drift,
+        [
+            Drift::Type {
+                table: "users",
+                column: "id",
+                declared: "Integer".to_owned(),
+                actual: "int8".to_owned(),
+            },
+            Drift::Nullability {
+                table: "users",
+                column: "hair_color",
+                declared_nullable: true,
+            },
+            Drift::MissingColumn {
+                table: "outbox",
+                column: "last_error",
+            },
+        ]
+    );
+    assert_eq!(
+        drift[1].to_string(),
+        "column `users.hair_color` is declared `Nullable` but is NOT NULL"
+    );
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::TestApp;
+use issue::hair_color::{HairColor, NAMES};
+use serde_json::json;
+
+fn canonical(input: &str) -> Option<String> {
+    input.parse::<HairColor>().ok().map(String::from)
+}
+
+#[test]
+fn names_and_hex_codes_are_canonicalized() {
+    assert_eq!(canonical("Brown").as_deref(), Some("brown"));
+    assert_eq!(canonical(" dark_Brown ").as_deref(), Some("dark brown"));
+    assert_eq!(
+        canonical("Strawberry-Blond").as_deref(),
+        Some("strawberry blonde")
+    );
+    assert_eq!(canonical("grey").as_deref(), Some("gray"));
+    assert_eq!(canonical("#A52A2A").as_deref(), Some("#a52a2a"));
+    assert_eq!(canonical("#AbC").as_deref(), Some("#aabbcc"));
+
+    for name in NAMES {
+        assert_eq!(canonical(name).as_deref(), Some(*name));
+    }
+    for invalid in ["", "purple", "brownish", "#abcd", "#ggg", "a52a2a", "# abc"] {
+        assert_eq!(canonical(invalid), None, "{invalid:?}");
+    }
+}
+
+#[test]
+fn serializes_as_its_canonical_string() {
+    let color: HairColor = serde_json::from_value(json!("Grey")).unwrap();
+    assert_eq!(serde_json::to_value(&color).unwrap(), json!("gray"));
+    assert!(serde_json::from_value::<HairColor>(json!("purple")).is_err());
+}
+
+#[tokio::test]
+async fn every_canonical_color_is_accepted_by_the_database() {
+    let app = TestApp::spawn().await;
+
+    for color in NAMES.iter().copied().chain(["#a52a2a"]) {
+        app.post("/users", json!({ "name": "Ada", "hair_color": color }))
+            .await
+            .assert_status(StatusCode::OK)
+            .assert_json(json!({ "hair_color": color }));
+    }
+}
+
+#[tokio::test]
+async fn written_colors_are_stored_canonically() {
+    let app = TestApp::spawn().await;
+
+    let created = app
+        .post("/users", json!({ "name": "Ada", "hair_color": "Blond" }))
+        .await;
+    created
+        .assert_status(StatusCode::OK)
+        .assert_json(json!({ "hair_color": "blonde" }));
+    let id = created.json::<serde_json::Value>()["id"].as_i64().unwrap();
+
+    app.patch(&format!("/users/{id}"), json!({ "hair_color": "#F0A" }))
+        .await
+        .assert_status(StatusCode::OK)
+        .assert_json(json!({ "hair_color": "#ff00aa" }));
+    app.get(&format!("/users/{id}"))
+        .await
+        .assert_json(json!({ "hair_color": "#ff00aa" }));
+
+    app.put(
+        &format!("/users/{id}"),
+        json!({ "name": "Ada", "hair_color": "purple" }),
+    )
+    .await
+    .assert_status(StatusCode::UNPROCESSABLE_ENTITY)
+    .assert_json(json!({ "errors": [{ "field": "hair_color", "code": "hair_color" }] }));
+}
+
+#[tokio::test]
+async fn search_rejects_unknown_hair_colors() {
+    let app = TestApp::spawn().await;
+
+    app.get("/users/search?hair_color=purple")
+        .await
+        .assert_status(StatusCode::BAD_REQUEST)
+        .assert_json(json!({ "code": "invalid_query" }));
+}
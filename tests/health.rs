@@ -0,0 +1,66 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::TestApp;
+use serde_json::json;
+
+#[tokio::test]
+async fn healthz_answers() {
+    let app = TestApp::spawn().await;
+
+    app.get("/healthz")
+        .await
+        .assert_status(StatusCode::OK)
+        .assert_json(json!({ "status": "ok" }));
+}
+
+#[tokio::test]
+async fn readyz_reports_every_dependency() {
+    let app = TestApp::spawn().await;
+
+    let response = app.get("/readyz").await;
+    response.assert_status(StatusCode::OK).assert_json(json!({
+        "status": "ready",
+        "checks": {
+            "database": { "status": "up" },
+            "pool": { "status": "up", "max_size": 1 },
+            "search": { "status": "up" },
+        },
+    }));
+    let body = response.json::<serde_json::Value>();
+    assert!(body["checks"]["database"]["latency_ms"].is_number());
+    assert!(body["checks"]["search"]["latency_ms"].is_number());
+}
+
+#[tokio::test]
+async fn readyz_is_degraded_without_meilisearch() {
+    let app = TestApp::spawn().await;
+    app.search.set_unavailable(true);
+
+    let response = app.get("/readyz").await;
+    response.assert_status(StatusCode::OK).assert_json(json!({
+        "status": "degraded",
+        "checks": {
+            "database": { "status": "up" },
+            "search": { "status": "down", "error": "search_unavailable" },
+        },
+    }));
+}
+
+#[tokio::test]
+async fn readyz_fails_when_the_pool_is_saturated() {
+    let app = TestApp::spawn().await;
+    // the test pool holds a single connection
+    let _held = app.pool.get().await.unwrap();
+
+    app.get("/readyz")
+        .await
+        .assert_status(StatusCode::SERVICE_UNAVAILABLE)
+        .assert_json(json!({
+            "status": "not_ready",
+            "checks": {
+                "database": { "status": "down", "error": "timeout" },
+                "pool": { "status": "saturated" },
+            },
+        }));
+}
@@ -0,0 +1,57 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::TestApp;
+use diesel_async::RunQueryDsl;
+use issue::backend::MeilisearchBackend;
+use serde_json::json;
+
+#[tokio::test]
+async fn flush_delivers_entries_left_by_failed_inline_indexing() {
+    let app = TestApp::spawn().await;
+
+    app.search.set_unavailable(true);
+    let created = app
+        .post("/users", json!({ "name": "Ada", "hair_color": null }))
+        .await;
+    created.assert_status(StatusCode::OK);
+    assert_eq!(created.header("x-index-status"), "unavailable");
+
+    let backend = MeilisearchBackend::new(app.search.client());
+    // nothing reaches the backend while it is down
+    assert_eq!(app.flush_outbox(&backend).await, 0);
+
+    app.search.set_unavailable(false);
+    assert_eq!(app.flush_outbox(&backend).await, 1);
+    assert_eq!(app.search.documents("users").len(), 1);
+    assert_eq!(app.flush_outbox(&backend).await, 0);
+}
+
+#[tokio::test]
+async fn worker_reindexes_from_the_current_row_after_inline_delivery() {
+    let app = TestApp::spawn().await;
+
+    let created = app
+        .post(
+            "/users?wait_for_index=true",
+            json!({ "name": "Ada", "hair_color": null }),
+        )
+        .await;
+    assert_eq!(created.header("x-index-status"), "succeeded");
+    let id = created.json::<serde_json::Value>()["id"].as_i64().unwrap();
+
+    // stands in for a concurrent write whose inline delivery lost the race
+    let mut conn = app.pool.get().await.unwrap();
+    diesel::sql_query(format!("UPDATE users SET name = 'Grace' WHERE id = {id}"))
+        .execute(&mut conn)
+        .await
+        .unwrap();
+    drop(conn);
+
+    let backend = MeilisearchBackend::new(app.search.client());
+    assert_eq!(app.flush_outbox(&backend).await, 1);
+    assert_eq!(
+        app.search.documents("users"),
+        vec![json!({ "id": id, "name": "Grace", "hair_color": null })]
+    );
+}
@@ -0,0 +1,120 @@
+mod common;
+
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use common::TestApp;
+use issue::backend::MeilisearchBackend;
+use serde_json::json;
+
+#[tokio::test]
+async fn metrics_cover_requests_queries_and_search_calls() {
+    let app = TestApp::spawn().await;
+
+    let id = app
+        .post("/users", json!({ "name": "Ada", "hair_color": null }))
+        .await
+        .json::<serde_json::Value>()["id"]
+        .as_i64()
+        .unwrap();
+    app.get(&format!("/users/{id}"))
+        .await
+        .assert_status(StatusCode::OK);
+
+    let response = app.get("/metrics").await;
+    response.assert_status(StatusCode::OK);
+    assert!(response.header("content-type").starts_with("text/plain"));
+    let body = String::from_utf8(response.body.to_vec()).unwrap();
+    for expected in [
+        r#"http_requests_total{method="POST",route="/users",status="200"} 1"#,
+        r#"http_requests_total{method="GET",route="/users/:id",status="200"} 1"#,
+        r#"db_query_duration_seconds_count{outcome="ok",query="users.insert"} 1"#,
+        r#"db_query_duration_seconds_count{outcome="ok",query="outbox.release"} 1"#,
+        r#"db_query_duration_seconds_count{outcome="ok",query="users.get"} 1"#,
+        r#"search_backend_calls_total{operation="upsert",outcome="ok"} 1"#,
+        "db_pool_max_size 1",
+        "db_pool_waiters 0",
+    ] {
+        assert!(body.contains(expected), "missing `{expected}` in:\n{body}");
+    }
+}
+
+#[tokio::test]
+async fn failed_search_calls_are_counted() {
+    let app = TestApp::spawn().await;
+    app.search.set_unavailable(true);
+
+    app.get("/users/search?q=ada")
+        .await
+        .assert_status(StatusCode::OK);
+
+    let body = String::from_utf8(app.get("/metrics").await.body.to_vec()).unwrap();
+    assert!(
+        body.contains(r#"search_backend_calls_total{operation="search",outcome="error"} 1"#),
+        "{body}"
+    );
+    assert!(
+        body.contains(r#"db_query_duration_seconds_count{outcome="ok",query="users.fulltext"} 1"#),
+        "{body}"
+    );
+}
+
+#[tokio::test]
+async fn outbox_deliveries_are_timed() {
+    let app = TestApp::spawn().await;
+    app.post("/users", json!({ "name": "Ada", "hair_color": null }))
+        .await
+        .assert_status(StatusCode::OK);
+
+    let backend = MeilisearchBackend::new(app.search.client());
+    app.search.set_unavailable(true);
+    assert_eq!(app.flush_outbox(&backend).await, 0);
+    app.search.set_unavailable(false);
+    assert_eq!(app.flush_outbox(&backend).await, 1);
+
+    let body = String::from_utf8(app.get("/metrics").await.body.to_vec()).unwrap();
+    for expected in [
+        r#"db_query_duration_seconds_count{outcome="ok",query="outbox.claim"} 3"#,
+        r#"db_query_duration_seconds_count{outcome="ok",query="users.load"} 3"#,
+        r#"db_query_duration_seconds_count{outcome="ok",query="outbox.backoff"} 1"#,
+        r#"db_query_duration_seconds_count{outcome="ok",query="outbox.delete"} 1"#,
+    ] {
+        assert!(body.contains(expected), "missing `{expected}` in:\n{body}");
+    }
+}
+
+#[tokio::test]
+async fn cancelled_pool_checkouts_stop_counting_as_waiters() {
+    let app = TestApp::spawn().await;
+    // the test pool holds a single connection
+    let held = app.pool.get().await.unwrap();
+
+    let request = app.get("/users/1");
+    assert!(tokio::time::timeout(Duration::from_millis(100), request)
+        .await
+        .is_err());
+    drop(held);
+
+    let body = String::from_utf8(app.get("/metrics").await.body.to_vec()).unwrap();
+    assert!(body.contains("db_pool_waiters 0"), "{body}");
+}
+
+#[tokio::test]
+async fn unknown_paths_and_methods_are_counted() {
+    let app = TestApp::spawn().await;
+
+    app.get("/nowhere")
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+    app.delete("/metrics")
+        .await
+        .assert_status(StatusCode::METHOD_NOT_ALLOWED);
+
+    let body = String::from_utf8(app.get("/metrics").await.body.to_vec()).unwrap();
+    for expected in [
+        r#"http_requests_total{method="GET",route="unmatched",status="404"} 1"#,
+        r#"http_requests_total{method="DELETE",route="/metrics",status="405"} 1"#,
+    ] {
+        assert!(body.contains(expected), "missing `{expected}` in:\n{body}");
+    }
+}
@@ -0,0 +1,163 @@
+use std::time::Duration;
+
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl, SimpleAsyncConnection};
+use issue::{config::DatabaseConfig, migrate, secret::Secret, DbPool};
+
+/// Runs `test` against a pool whose search path points at a fresh, empty
+/// schema, dropping the schema afterwards.
+async fn with_empty_schema<F, Fut>(test: F)
+where
+    F: FnOnce(DbPool) -> Fut + Send + 'static,
+    Fut: std::future::Future<Output = ()> + Send,
+{
+    let base_url = std::env::var("TEST_DATABASE_URL")
+        .or_else(|_| std::env::var("DATABASE_URL"))
+        .expect("set TEST_DATABASE_URL to a Postgres database the tests may write to");
+    let schema = format!("test_{}", uuid::Uuid::new_v4().simple());
+    let mut admin = AsyncPgConnection::establish(&base_url).await.unwrap();
+    admin
+        .batch_execute(&format!("CREATE SCHEMA {schema}"))
+        .await
+        .unwrap();
+
+    let mut url = url::Url::parse(&base_url).unwrap();
+    url.query_pairs_mut()
+        .append_pair("options", &format!("-csearch_path={schema}"));
+    let config = DatabaseConfig {
+        url: Secret::new(url.into()),
+        max_connections: 2,
+        min_idle: None,
+        connection_timeout: Duration::from_secs(10),
+        run_migrations: false,
+    };
+    let pool = issue::connect(&config).await.unwrap();
+    // drop the schema even when an assertion fails
+    let outcome = tokio::spawn(async move { test(pool).await }).await;
+    admin
+        .batch_execute(&format!("DROP SCHEMA {schema} CASCADE"))
+        .await
+        .unwrap();
+    if let Err(panic) = outcome {
+        std::panic::resume_unwind(panic.into_panic());
+    }
+}
+
+#[tokio::test]
+async fn migrations_apply_revert_and_redo() {
+    with_empty_schema(|pool| async move {
+        let all = migrate::pending(&pool).await.unwrap();
+        assert!(!all.is_empty());
+
+        assert_eq!(migrate::run_pending(&pool).await.unwrap(), all);
+        assert!(migrate::pending(&pool).await.unwrap().is_empty());
+        assert!(migrate::status(&pool)
+            .await
+            .unwrap()
+            .iter()
+            .all(|migration| migration.applied));
+
+        let last = migrate::revert_last(&pool).await.unwrap();
+        assert_eq!(
+            migrate::pending(&pool).await.unwrap(),
+            std::slice::from_ref(&last)
+        );
+
+        migrate::run_pending(&pool).await.unwrap();
+        assert_eq!(migrate::redo(&pool).await.unwrap(), last);
+        assert!(migrate::pending(&pool).await.unwrap().is_empty());
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn hair_colors_are_mapped_to_canonical_form() {
+    with_empty_schema(|pool| async move {
+        migrate::run_pending(&pool).await.unwrap();
+        while migrate::revert_last(&pool).await.unwrap() != "20261015110000" {}
+
+        let mut conn = pool.get().await.unwrap();
+        conn.batch_execute(
+            "INSERT INTO users (id, name, hair_color) VALUES \
+             (1, 'a', 'brown'), (2, 'b', ' Dark_Brown '), (3, 'c', 'Grey'), \
+             (4, 'd', '#ABC'), (5, 'e', 'purple'), (6, 'f', NULL)",
+        )
+        .await
+        .unwrap();
+
+        migrate::run_pending(&pool).await.unwrap();
+        assert_eq!(
+            hair_colors(&mut conn, "SELECT id, hair_color FROM users ORDER BY id").await,
+            [
+                (1, Some("brown".to_owned())),
+                (2, Some("dark brown".to_owned())),
+                (3, Some("gray".to_owned())),
+                (4, Some("#aabbcc".to_owned())),
+                (5, None),
+                (6, None),
+            ]
+        );
+        assert_eq!(
+            hair_colors(
+                &mut conn,
+                "SELECT user_id AS id, original || ' -> ' || coalesce(mapped, '(cleared)') \
+                 AS hair_color FROM hair_color_mapping ORDER BY user_id"
+            )
+            .await,
+            [
+                (2, Some(" Dark_Brown  -> dark brown".to_owned())),
+                (3, Some("Grey -> gray".to_owned())),
+                (4, Some("#ABC -> #aabbcc".to_owned())),
+                (5, Some("purple -> (cleared)".to_owned())),
+            ]
+        );
+        assert_eq!(
+            hair_colors(
+                &mut conn,
+                "SELECT user_id AS id, NULL AS hair_color FROM outbox ORDER BY user_id"
+            )
+            .await
+            .len(),
+            4
+        );
+        assert!(conn
+            .batch_execute("UPDATE users SET hair_color = 'Purple' WHERE id = 1")
+            .await
+            .is_err());
+
+        // rows edited since the migration keep their new value
+        conn.batch_execute("UPDATE users SET hair_color = 'red' WHERE id = 3")
+            .await
+            .unwrap();
+        while migrate::revert_last(&pool).await.unwrap() != "20261015110000" {}
+        assert_eq!(
+            hair_colors(&mut conn, "SELECT id, hair_color FROM users ORDER BY id").await,
+            [
+                (1, Some("brown".to_owned())),
+                (2, Some(" Dark_Brown ".to_owned())),
+                (3, Some("red".to_owned())),
+                (4, Some("#ABC".to_owned())),
+                (5, Some("purple".to_owned())),
+                (6, None),
+            ]
+        );
+    })
+    .await;
+}
+
+async fn hair_colors(conn: &mut AsyncPgConnection, query: &str) -> Vec<(i32, Option<String>)> {
+    #[derive(diesel::QueryableByName)]
+    struct Row {
+        #[diesel(sql_type = diesel::sql_types::Integer)]
+        id: i32,
+        #[diesel(sql_type = diesel::sql_types::Nullable<diesel::sql_types::Text>)]
+        hair_color: Option<String>,
+    }
+
+    diesel::sql_query(query)
+        .load::<Row>(conn)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|row| (row.id, row.hair_color))
+        .collect()
+}
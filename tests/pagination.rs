@@ -0,0 +1,127 @@
+mod common;
+
+use axum::http::StatusCode;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use common::TestApp;
+use serde_json::{json, Value};
+
+async fn create(app: &TestApp, names: &[&str]) -> Vec<i64> {
+    let mut ids = Vec::new();
+    for name in names {
+        let created = app
+            .post("/users", json!({ "name": name, "hair_color": null }))
+            .await;
+        created.assert_status(StatusCode::OK);
+        ids.push(created.json::<Value>()["id"].as_i64().unwrap());
+    }
+    ids
+}
+
+/// Follows `Link: rel="next"` from `first`, returning every page.
+async fn walk(app: &TestApp, first: &str) -> Vec<Value> {
+    let mut pages = Vec::new();
+    let mut uri = Some(first.to_owned());
+    while let Some(current) = uri.take() {
+        let response = app.get(&current).await;
+        response.assert_status(StatusCode::OK);
+        let page = response.json::<Value>();
+        match &page["next_cursor"] {
+            Value::Null => assert!(response.headers.get("link").is_none()),
+            Value::String(cursor) => {
+                let link = response.header("link");
+                assert!(link.ends_with(r#">; rel="next""#), "{link}");
+                assert!(link.contains(&format!("cursor={cursor}>")), "{link}");
+                uri = Some(link[1..link.find('>').unwrap()].to_owned());
+            }
+            other => panic!("unexpected next_cursor {other}"),
+        }
+        pages.push(page);
+    }
+    pages
+}
+
+fn ids(pages: &[Value]) -> Vec<i64> {
+    pages
+        .iter()
+        .flat_map(|page| page["users"].as_array().unwrap())
+        .map(|user| user["id"].as_i64().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn pages_walk_every_user_in_id_order() {
+    let app = TestApp::spawn().await;
+    let created = create(&app, &["Ada", "Grace", "Edsger", "Barbara", "Alan"]).await;
+
+    let pages = walk(&app, "/users?limit=2").await;
+    assert_eq!(
+        pages
+            .iter()
+            .map(|page| page["users"].as_array().unwrap().len())
+            .collect::<Vec<_>>(),
+        [2, 2, 1]
+    );
+    assert_eq!(ids(&pages), created);
+}
+
+#[tokio::test]
+async fn pages_walk_every_user_in_name_order() {
+    let app = TestApp::spawn().await;
+    let created = create(&app, &["Grace", "Ada", "Grace", "Alan"]).await;
+
+    let pages = walk(&app, "/users?limit=1&order=name").await;
+    // equal names are ordered by id
+    assert_eq!(
+        ids(&pages),
+        [created[1], created[3], created[0], created[2]]
+    );
+}
+
+#[tokio::test]
+async fn limit_is_capped() {
+    let app = TestApp::spawn().await;
+    create(&app, &["Ada", "Grace"]).await;
+
+    let response = app.get("/users?limit=0").await;
+    response
+        .assert_status(StatusCode::OK)
+        .assert_json(json!({ "users": [{ "name": "Ada" }] }));
+    assert!(response.header("link").starts_with("</users?limit=1&"));
+
+    app.get("/users?limit=100000")
+        .await
+        .assert_status(StatusCode::OK)
+        .assert_json(
+            json!({ "users": [{ "name": "Ada" }, { "name": "Grace" }], "next_cursor": null }),
+        );
+}
+
+#[tokio::test]
+async fn tampered_or_mismatched_cursors_are_rejected() {
+    let app = TestApp::spawn().await;
+    create(&app, &["Ada", "Grace"]).await;
+    let page = app.get("/users?limit=1").await.json::<Value>();
+    let cursor = page["next_cursor"].as_str().unwrap();
+
+    let (payload, signature) = cursor.split_once('.').unwrap();
+    // a well-formed cursor that the service never signed
+    let forged = format!(
+        "{}.{signature}",
+        URL_SAFE_NO_PAD.encode(r#"{"order":"id","id":0}"#)
+    );
+    for cursor in [forged.as_str(), payload, "garbage"] {
+        app.get(&format!("/users?cursor={cursor}"))
+            .await
+            .assert_status(StatusCode::BAD_REQUEST)
+            .assert_json(json!({ "code": "invalid_cursor" }));
+    }
+
+    app.get(&format!("/users?cursor={cursor}&order=name"))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST)
+        .assert_json(json!({ "code": "invalid_cursor" }));
+    app.get(&format!("/users?cursor={cursor}&order=id"))
+        .await
+        .assert_status(StatusCode::OK)
+        .assert_json(json!({ "users": [{ "name": "Grace" }] }));
+}
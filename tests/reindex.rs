@@ -0,0 +1,214 @@
+mod common;
+
+use std::{sync::Mutex, time::Duration};
+
+use axum::{async_trait, http::StatusCode};
+use common::TestApp;
+use issue::{
+    backend::{InMemoryBackend, SearchBackend, SearchError, SearchPage, SearchRequest, TaskState},
+    models::User,
+    reindex,
+};
+use serde_json::json;
+use tokio::sync::Notify;
+
+/// An in-memory backend that records upsert batch sizes and can fail the
+/// upsert with a given number (starting at 1), or pause before it until
+/// `resume` is notified.
+#[derive(Default)]
+struct Recording {
+    inner: InMemoryBackend,
+    upserts: Mutex<Vec<usize>>,
+    fail_upsert: Option<usize>,
+    pause_upsert: Option<usize>,
+    paused: Notify,
+    resume: Notify,
+}
+
+#[async_trait]
+impl SearchBackend for Recording {
+    async fn configure(&self, index: &str) -> Result<u32, SearchError> {
+        self.inner.configure(index).await
+    }
+
+    async fn upsert(&self, index: &str, users: &[User]) -> Result<u32, SearchError> {
+        let attempt = {
+            let mut upserts = self.upserts.lock().unwrap();
+            upserts.push(users.len());
+            upserts.len()
+        };
+        if self.fail_upsert == Some(attempt) {
+            return Err(SearchError::Timeout(0));
+        }
+        if self.pause_upsert == Some(attempt) {
+            self.paused.notify_one();
+            self.resume.notified().await;
+        }
+        self.inner.upsert(index, users).await
+    }
+
+    async fn delete(&self, index: &str, ids: &[i32]) -> Result<u32, SearchError> {
+        self.inner.delete(index, ids).await
+    }
+
+    async fn search(
+        &self,
+        index: &str,
+        request: &SearchRequest<'_>,
+    ) -> Result<SearchPage, SearchError> {
+        self.inner.search(index, request).await
+    }
+
+    async fn health(&self) -> Result<(), SearchError> {
+        self.inner.health().await
+    }
+
+    async fn task(&self, uid: u32) -> Result<Option<TaskState>, SearchError> {
+        self.inner.task(uid).await
+    }
+
+    async fn wait(&self, uid: u32, timeout: Duration) -> Result<TaskState, SearchError> {
+        self.inner.wait(uid, timeout).await
+    }
+
+    async fn ensure_index(&self, index: &str, timeout: Duration) -> Result<(), SearchError> {
+        self.inner.ensure_index(index, timeout).await
+    }
+
+    async fn create_index(&self, index: &str) -> Result<u32, SearchError> {
+        self.inner.create_index(index).await
+    }
+
+    async fn delete_index(&self, index: &str) -> Result<u32, SearchError> {
+        self.inner.delete_index(index).await
+    }
+
+    async fn swap_indexes(&self, a: &str, b: &str) -> Result<u32, SearchError> {
+        self.inner.swap_indexes(a, b).await
+    }
+}
+
+async fn create_users(app: &TestApp, count: usize) -> Vec<i64> {
+    let mut ids = Vec::new();
+    for i in 0..count {
+        let created = app
+            .post(
+                "/users",
+                json!({ "name": format!("User {i}"), "hair_color": null }),
+            )
+            .await;
+        created.assert_status(StatusCode::OK);
+        ids.push(created.json::<serde_json::Value>()["id"].as_i64().unwrap());
+    }
+    ids
+}
+
+#[tokio::test]
+async fn reindex_fills_a_staging_index_in_batches_and_swaps_it_in() {
+    let app = TestApp::spawn().await;
+    create_users(&app, 5).await;
+    let backend = Recording::default();
+    // a stale document that the rebuilt index must not carry over
+    backend
+        .inner
+        .ensure_index("users", Duration::ZERO)
+        .await
+        .unwrap();
+    backend
+        .inner
+        .upsert(
+            "users",
+            &[User {
+                id: -1,
+                name: "Gone".to_owned(),
+                hair_color: None,
+            }],
+        )
+        .await
+        .unwrap();
+
+    reindex::run(&app.pool, &backend, 2).await.unwrap();
+
+    assert_eq!(*backend.upserts.lock().unwrap(), [2, 2, 1]);
+    let names: Vec<String> = backend
+        .inner
+        .documents("users")
+        .into_iter()
+        .map(|user| user.name)
+        .collect();
+    assert_eq!(names, ["User 0", "User 1", "User 2", "User 3", "User 4"]);
+    assert_eq!(backend.inner.indexes(), ["users"]);
+}
+
+#[tokio::test]
+async fn failed_reindex_removes_the_staging_index_and_keeps_the_live_one() {
+    let app = TestApp::spawn().await;
+    create_users(&app, 3).await;
+    let backend = Recording {
+        fail_upsert: Some(2),
+        ..Recording::default()
+    };
+    backend
+        .inner
+        .ensure_index("users", Duration::ZERO)
+        .await
+        .unwrap();
+    backend
+        .inner
+        .upsert(
+            "users",
+            &[User {
+                id: -1,
+                name: "Old".to_owned(),
+                hair_color: None,
+            }],
+        )
+        .await
+        .unwrap();
+
+    assert!(reindex::run(&app.pool, &backend, 2).await.is_err());
+
+    assert_eq!(backend.inner.indexes(), ["users"]);
+    let names: Vec<String> = backend
+        .inner
+        .documents("users")
+        .into_iter()
+        .map(|user| user.name)
+        .collect();
+    assert_eq!(names, ["Old"]);
+}
+
+#[tokio::test]
+async fn writes_during_a_reindex_reach_the_rebuilt_index() {
+    let app = TestApp::spawn().await;
+    let ids = create_users(&app, 4).await;
+    let backend = Recording {
+        pause_upsert: Some(2),
+        ..Recording::default()
+    };
+
+    let rebuild = reindex::run(&app.pool, &backend, 2);
+    let writes = async {
+        // the first batch is in the staging index
+        backend.paused.notified().await;
+        app.patch(&format!("/users/{}", ids[0]), json!({ "name": "Renamed" }))
+            .await
+            .assert_status(StatusCode::OK);
+        let uri = format!("/users/{}", ids[1]);
+        app.delete(&uri).await.assert_status(StatusCode::NO_CONTENT);
+        // delivered now, the writes would only reach the index being replaced
+        assert_eq!(app.flush_outbox(&backend).await, 0);
+        backend.resume.notify_one();
+    };
+    let (rebuilt, ()) = tokio::join!(rebuild, writes);
+    rebuilt.unwrap();
+
+    assert!(app.flush_outbox(&backend).await > 0);
+    let names: Vec<String> = backend
+        .inner
+        .documents("users")
+        .into_iter()
+        .map(|user| user.name)
+        .collect();
+    assert_eq!(names, ["Renamed", "User 2", "User 3"]);
+}
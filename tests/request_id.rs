@@ -0,0 +1,53 @@
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use common::TestApp;
+use serde_json::json;
+
+#[tokio::test]
+async fn request_id_is_propagated_to_response_and_problem() {
+    let app = TestApp::spawn().await;
+
+    let request = Request::get("/users/999")
+        .header("x-request-id", "ticket-1234")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.request(request).await;
+    response
+        .assert_status(StatusCode::NOT_FOUND)
+        .assert_json(json!({ "request_id": "ticket-1234" }));
+    assert_eq!(response.header("x-request-id"), "ticket-1234");
+}
+
+#[tokio::test]
+async fn request_id_is_generated_when_missing() {
+    let app = TestApp::spawn().await;
+
+    let response = app.get("/users/999").await;
+    let id = response.header("x-request-id");
+    assert!(uuid::Uuid::parse_str(id).is_ok(), "not a UUID: {id}");
+    response.assert_json(json!({ "request_id": id }));
+
+    // unmatched routes get one too
+    let response = app.get("/no-such-route").await;
+    response.assert_status(StatusCode::NOT_FOUND);
+    assert!(uuid::Uuid::parse_str(response.header("x-request-id")).is_ok());
+}
+
+#[tokio::test]
+async fn unusable_request_id_is_replaced() {
+    let app = TestApp::spawn().await;
+
+    for id in ["has spaces".to_owned(), "x".repeat(200)] {
+        let request = Request::get("/users")
+            .header("x-request-id", id.as_str())
+            .body(Body::empty())
+            .unwrap();
+        let response = app.request(request).await;
+        response.assert_status(StatusCode::OK);
+        assert!(uuid::Uuid::parse_str(response.header("x-request-id")).is_ok());
+    }
+}
@@ -0,0 +1,120 @@
+use std::time::Duration;
+
+use issue::{
+    backend::{InMemoryBackend, SearchBackend, SearchError, SearchRequest},
+    models::User,
+    search::{IndexStatus, SearchSort},
+};
+
+fn user(id: i32, name: &str, hair_color: Option<&str>) -> User {
+    User {
+        id,
+        name: name.to_owned(),
+        hair_color: hair_color.map(|color| color.parse().unwrap()),
+    }
+}
+
+fn request(query: &str) -> SearchRequest<'_> {
+    SearchRequest {
+        query,
+        hair_color: None,
+        sort: None,
+        page: 1,
+        hits_per_page: 20,
+    }
+}
+
+#[tokio::test]
+async fn in_memory_backend_indexes_searches_and_deletes() {
+    let backend = InMemoryBackend::new();
+    let task = backend
+        .upsert(
+            "users",
+            &[
+                user(1, "Ada Lovelace", Some("brown")),
+                user(2, "Alan Turing", Some("black")),
+                user(3, "Ada Yonath", Some("grey")),
+            ],
+        )
+        .await
+        .unwrap();
+    let state = backend.wait(task, Duration::from_secs(1)).await.unwrap();
+    assert_eq!(state.status, IndexStatus::Succeeded);
+
+    let page = backend
+        .search(
+            "users",
+            &SearchRequest {
+                sort: Some(SearchSort::IdDesc),
+                ..request("ada")
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(page.total_hits, 2);
+    assert_eq!(
+        page.hits.iter().map(|hit| hit.id).collect::<Vec<_>>(),
+        [3, 1]
+    );
+    assert_eq!(page.hits[1].highlight["name"], "<em>Ada</em> Lovelace");
+
+    let page = backend
+        .search(
+            "users",
+            &SearchRequest {
+                hair_color: Some("black"),
+                ..request("")
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(page.hits.iter().map(|hit| hit.id).collect::<Vec<_>>(), [2]);
+
+    backend.delete("users", &[1]).await.unwrap();
+    let ids: Vec<i32> = backend.documents("users").iter().map(|u| u.id).collect();
+    assert_eq!(ids, [2, 3]);
+}
+
+#[tokio::test]
+async fn in_memory_backend_reports_failed_tasks() {
+    let backend = InMemoryBackend::new();
+    backend.create_index("users").await.unwrap();
+    let task = backend.create_index("users").await.unwrap();
+    assert!(matches!(
+        backend.wait_for_success(task, Duration::from_secs(1)).await,
+        Err(SearchError::TaskFailed { .. })
+    ));
+    assert!(backend.task(99).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn in_memory_backend_swaps_indexes() {
+    let backend = InMemoryBackend::new();
+    backend
+        .upsert("users_staging", &[user(7, "Grace Hopper", None)])
+        .await
+        .unwrap();
+    backend
+        .swap_indexes("users", "users_staging")
+        .await
+        .unwrap();
+    assert_eq!(backend.documents("users").len(), 1);
+    assert!(backend.documents("users_staging").is_empty());
+}
+
+#[tokio::test]
+async fn in_memory_backend_forgets_old_tasks() {
+    let backend = InMemoryBackend::new();
+    let first = backend.configure("users").await.unwrap();
+    let mut last = first;
+    for _ in 0..1000 {
+        last = backend.configure("users").await.unwrap();
+    }
+    assert!(backend.task(first).await.unwrap().is_none());
+    assert!(matches!(
+        backend.wait(first, Duration::from_secs(1)).await,
+        Err(SearchError::Timeout(uid)) if uid == first
+    ));
+    assert!(backend.task(first + 1).await.unwrap().is_some());
+    assert_eq!(backend.task(last).await.unwrap().unwrap().uid, last);
+}
@@ -0,0 +1,76 @@
+mod common;
+
+use axum::{
+    body::Body,
+    http::{header, Request, StatusCode},
+};
+use common::{fake_collector::FakeCollector, TestApp};
+use issue::{config::TelemetryConfig, telemetry};
+use opentelemetry::trace::TracerProvider as _;
+use serde_json::json;
+use tracing_subscriber::layer::SubscriberExt;
+
+const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+const PARENT_SPAN_ID: &str = "00f067aa0ba902b7";
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+// the batch exporter runs on a worker thread while the test thread flushes
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn create_user_continues_the_callers_trace() {
+    let app = TestApp::spawn().await;
+    // isahc spawns its agent thread on a client's first request, under a span
+    // that lives as long as the client and would keep its parents open; serve
+    // makes that first call at startup, so do the same here
+    app.get("/readyz").await.assert_status(StatusCode::OK);
+    let collector = FakeCollector::start().await;
+    let provider = telemetry::tracer_provider(&TelemetryConfig {
+        otlp_endpoint: Some(collector.url().to_owned()),
+        service_name: "issue-test".to_owned(),
+    })
+    .unwrap()
+    .expect("an endpoint is configured");
+    let subscriber = tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+    // global rather than thread-local: the Meilisearch client closes its spans
+    // on its agent thread, and parents are closed through the default there
+    tracing::subscriber::set_global_default(subscriber).unwrap();
+
+    let request = Request::post("/users")
+        .header(header::CONTENT_TYPE, "application/json")
+        .header("traceparent", format!("00-{TRACE_ID}-{PARENT_SPAN_ID}-01"))
+        .body(Body::from(
+            json!({ "name": "Ada", "hair_color": null }).to_string(),
+        ))
+        .unwrap();
+    app.request(request).await.assert_status(StatusCode::OK);
+    for result in provider.force_flush() {
+        result.expect("spans were exported");
+    }
+
+    let spans: Vec<_> = collector
+        .spans()
+        .into_iter()
+        .filter(|span| hex(&span.trace_id) == TRACE_ID)
+        .collect();
+    let names: Vec<&str> = spans.iter().map(|span| span.name.as_str()).collect();
+    for expected in ["POST /users", "db.pool.checkout", "users.insert", "upsert"] {
+        assert!(
+            names.contains(&expected),
+            "no `{expected}` span in {names:?}"
+        );
+    }
+
+    let server = spans
+        .iter()
+        .find(|span| span.name == "POST /users")
+        .unwrap();
+    assert_eq!(hex(&server.parent_span_id), PARENT_SPAN_ID);
+    let insert = spans
+        .iter()
+        .find(|span| span.name == "users.insert")
+        .unwrap();
+    assert_eq!(insert.parent_span_id, server.span_id);
+}
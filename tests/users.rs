@@ -0,0 +1,190 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::TestApp;
+use serde_json::json;
+
+#[tokio::test]
+async fn create_user_persists_and_indexes() {
+    let app = TestApp::spawn().await;
+
+    let created = app
+        .post(
+            "/users?wait_for_index=true",
+            json!({ "name": "Ada", "hair_color": "black" }),
+        )
+        .await;
+    created
+        .assert_status(StatusCode::OK)
+        .assert_json(json!({ "name": "Ada", "hair_color": "black" }));
+    assert_eq!(created.header("x-index-status"), "succeeded");
+
+    let id = created.json::<serde_json::Value>()["id"].as_i64().unwrap();
+    app.get(&format!("/users/{id}"))
+        .await
+        .assert_status(StatusCode::OK)
+        .assert_json(json!({ "id": id, "name": "Ada", "hair_color": "black" }));
+    assert_eq!(
+        app.search.documents("users"),
+        vec![json!({ "id": id, "name": "Ada", "hair_color": "black" })]
+    );
+}
+
+#[tokio::test]
+async fn update_and_delete_user() {
+    let app = TestApp::spawn().await;
+
+    let id = app
+        .post("/users", json!({ "name": "Grace", "hair_color": null }))
+        .await
+        .json::<serde_json::Value>()["id"]
+        .as_i64()
+        .unwrap();
+
+    app.patch(&format!("/users/{id}"), json!({ "hair_color": "grey" }))
+        .await
+        .assert_status(StatusCode::OK)
+        .assert_json(json!({ "name": "Grace", "hair_color": "gray" }));
+
+    app.delete(&format!("/users/{id}"))
+        .await
+        .assert_status(StatusCode::NO_CONTENT);
+    app.get(&format!("/users/{id}"))
+        .await
+        .assert_status(StatusCode::NOT_FOUND)
+        .assert_json(json!({
+            "status": 404,
+            "code": "not_found",
+            "instance": format!("/users/{id}"),
+        }));
+    assert!(app.search.documents("users").is_empty());
+}
+
+#[tokio::test]
+async fn invalid_body_is_a_problem() {
+    let app = TestApp::spawn().await;
+
+    let response = app.post("/users", json!({ "hair_color": "red" })).await;
+    response
+        .assert_status(StatusCode::UNPROCESSABLE_ENTITY)
+        .assert_json(json!({ "code": "invalid_body", "instance": "/users" }));
+    assert_eq!(response.header("content-type"), "application/problem+json");
+}
+
+#[tokio::test]
+async fn search_hydrates_hits_from_postgres() {
+    let app = TestApp::spawn().await;
+
+    for name in ["Ada Lovelace", "Alan Turing"] {
+        app.post("/users", json!({ "name": name, "hair_color": "brown" }))
+            .await
+            .assert_status(StatusCode::OK);
+    }
+
+    app.get("/users/search?q=lovelace")
+        .await
+        .assert_status(StatusCode::OK)
+        .assert_json(json!({
+            "total_hits": 1,
+            "degraded": false,
+            "hits": [{
+                "user": { "name": "Ada Lovelace" },
+                "highlight": { "name": "Ada <em>Lovelace</em>" },
+            }],
+        }));
+}
+
+#[tokio::test]
+async fn search_falls_back_to_postgres_when_meilisearch_is_down() {
+    let app = TestApp::spawn().await;
+
+    app.post(
+        "/users",
+        json!({ "name": "Ada Lovelace", "hair_color": null }),
+    )
+    .await
+    .assert_status(StatusCode::OK);
+    app.search.set_unavailable(true);
+
+    app.get("/users/search?q=lovelace")
+        .await
+        .assert_status(StatusCode::OK)
+        .assert_json(json!({
+            "total_hits": 1,
+            "degraded": true,
+            "hits": [{ "user": { "name": "Ada Lovelace" } }],
+        }));
+}
+
+#[tokio::test]
+async fn search_falls_back_to_postgres_when_meilisearch_hangs() {
+    let app = TestApp::spawn().await;
+
+    app.post(
+        "/users",
+        json!({ "name": "Ada Lovelace", "hair_color": null }),
+    )
+    .await
+    .assert_status(StatusCode::OK);
+    app.search.set_hanging(true);
+
+    let search = app.get("/users/search?q=lovelace");
+    tokio::time::timeout(std::time::Duration::from_secs(10), search)
+        .await
+        .expect("search waited on the hung backend")
+        .assert_status(StatusCode::OK)
+        .assert_json(json!({ "total_hits": 1, "degraded": true }));
+}
+
+#[tokio::test]
+async fn writes_answer_when_meilisearch_hangs() {
+    let app = TestApp::spawn().await;
+    app.search.set_hanging(true);
+
+    let create = app.post("/users", json!({ "name": "Ada", "hair_color": null }));
+    let created = tokio::time::timeout(std::time::Duration::from_secs(10), create)
+        .await
+        .expect("create waited on the hung backend");
+    created.assert_status(StatusCode::OK);
+    assert_eq!(created.header("x-index-status"), "timed_out");
+    let id = created.json::<serde_json::Value>()["id"].as_i64().unwrap();
+
+    let uri = format!("/users/{id}?wait_for_index=true");
+    let delete = app.delete(&uri);
+    let deleted = tokio::time::timeout(std::time::Duration::from_secs(10), delete)
+        .await
+        .expect("delete waited on the hung backend");
+    deleted.assert_status(StatusCode::NO_CONTENT);
+    assert_eq!(deleted.header("x-index-status"), "timed_out");
+
+    // the test pool's only connection was not left with the hung requests
+    app.get(&format!("/users/{id}"))
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn huge_search_pages_are_capped() {
+    let app = TestApp::spawn().await;
+
+    let uri = format!("/users/search?q=a&page={}", usize::MAX);
+    app.get(&uri)
+        .await
+        .assert_status(StatusCode::OK)
+        .assert_json(json!({ "page": 10_000, "hits": [] }));
+    app.search.set_unavailable(true);
+    app.get(&uri)
+        .await
+        .assert_status(StatusCode::OK)
+        .assert_json(json!({ "page": 10_000, "hits": [], "degraded": true }));
+}
+
+#[tokio::test]
+async fn each_test_starts_with_no_users() {
+    let app = TestApp::spawn().await;
+
+    app.get("/users")
+        .await
+        .assert_status(StatusCode::OK)
+        .assert_json(json!({ "users": [], "next_cursor": null }));
+}
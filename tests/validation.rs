@@ -0,0 +1,81 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::TestApp;
+use serde_json::json;
+
+#[tokio::test]
+async fn invalid_fields_are_listed() {
+    let app = TestApp::spawn().await;
+
+    let response = app
+        .post(
+            "/users",
+            json!({ "name": "x".repeat(101), "hair_color": "red!" }),
+        )
+        .await;
+    response
+        .assert_status(StatusCode::UNPROCESSABLE_ENTITY)
+        .assert_json(json!({
+            "code": "validation_failed",
+            "errors": [
+                { "field": "hair_color", "code": "hair_color" },
+                { "field": "name", "code": "length" },
+            ],
+        }));
+    assert_eq!(response.header("content-type"), "application/problem+json");
+}
+
+#[tokio::test]
+async fn blank_name_is_rejected_after_trimming() {
+    let app = TestApp::spawn().await;
+
+    app.post("/users", json!({ "name": "   ", "hair_color": null }))
+        .await
+        .assert_status(StatusCode::UNPROCESSABLE_ENTITY)
+        .assert_json(json!({ "errors": [{ "field": "name", "code": "length" }] }));
+}
+
+#[tokio::test]
+async fn input_is_trimmed_and_normalized() {
+    let app = TestApp::spawn().await;
+
+    // "e" followed by a combining acute accent
+    app.post(
+        "/users",
+        json!({ "name": "  Jose\u{301} ", "hair_color": " dark brown " }),
+    )
+    .await
+    .assert_status(StatusCode::OK)
+    .assert_json(json!({ "name": "Jos\u{e9}", "hair_color": "dark brown" }));
+}
+
+#[tokio::test]
+async fn partial_updates_are_validated() {
+    let app = TestApp::spawn().await;
+
+    let id = app
+        .post("/users", json!({ "name": "Ada", "hair_color": null }))
+        .await
+        .json::<serde_json::Value>()["id"]
+        .as_i64()
+        .unwrap();
+    app.patch(&format!("/users/{id}"), json!({ "hair_color": "" }))
+        .await
+        .assert_status(StatusCode::UNPROCESSABLE_ENTITY)
+        .assert_json(json!({ "errors": [{ "field": "hair_color", "code": "hair_color" }] }));
+    app.patch(&format!("/users/{id}"), json!({ "hair_color": null }))
+        .await
+        .assert_status(StatusCode::OK);
+}
+
+#[tokio::test]
+async fn invalid_body_does_not_wait_for_a_connection() {
+    let app = TestApp::spawn().await;
+    // the test pool holds a single connection
+    let _held = app.pool.get().await.unwrap();
+
+    app.post("/users", json!({ "name": "", "hair_color": null }))
+        .await
+        .assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+}